@@ -1,10 +1,17 @@
 // Assembler for C64 assembly language
 
-mod opcodes;
-
-use std::collections::HashMap;
-use crate::ast::{Ast, Instruction, Opcode, AddressingMode};
-use self::opcodes::build_opcode_table;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use crate::encoding::{Charmap, Encoding};
+use crate::ast::{is_anonymous_label, AddressWidth, Ast, BinaryOp, Directive, DirectiveArg, Expr, TextChar, Instruction, Opcode, Operand, AddressingMode, Span, Statement, UnaryOp};
+use crate::listing::ListingEntry;
+use crate::output::OutputFormat;
+use crate::parser::parse_directive_args;
+use crate::symbols::{Symbol, SymbolKind};
+use crate::isa::{inverse_branch, lookup_mnemonic, short_branch, Cpu, InstructionSet, OpcodeEntry, Register};
 
 #[derive(Debug, thiserror::Error)]
 pub enum AssemblerError {
@@ -14,6 +21,9 @@ pub enum AssemblerError {
     #[error("Invalid addressing mode for opcode: {0}")]
     InvalidAddressingMode(String),
     
+    #[error("{0} is not available on the {1} (select another CPU with .cpu)")]
+    UnsupportedInstruction(Opcode, String),
+    
     #[error("Unknown label: {0}")]
     UnknownLabel(String),
     
@@ -32,14 +42,59 @@ pub enum AssemblerError {
     #[error("Forward reference error: {0}")]
     ForwardReference(String),
     
-    #[error("Duplicate label error: {0}")]
+    #[error("Duplicate symbol: {0}")]
     DuplicateLabel(String),
     
     #[error("Invalid expression: {0}")]
     InvalidExpression(String),
     
-    #[error("Error at line {line}: {message}")]
-    SourceLineError { line: usize, message: String },
+    #[error("Character {0:?} has no mapping in the {1} encoding")]
+    UnmappedCharacter(char, Encoding),
+    
+    #[error("Control code ${0:02X} has no mapping in the {1} encoding")]
+    UnmappedControlCode(u8, Encoding),
+    
+    #[error("Cannot read character map file {0}: {1}")]
+    CharmapFile(String, String),
+    
+    #[error("{span}: {error}")]
+    SourceError { span: Span, error: Box<AssemblerError> },
+}
+
+impl AssemblerError {
+    /// Attach the source location of the statement that caused the error
+    fn at(self, span: &Span) -> Self {
+        match self {
+            AssemblerError::SourceError { .. } => self,
+            error => AssemblerError::SourceError {
+                span: span.clone(),
+                error: Box::new(error),
+            },
+        }
+    }
+}
+
+/// Instruction with its mnemonic resolved by the instruction set in use
+#[derive(Debug, Clone)]
+struct ResolvedInstruction {
+    opcode: Opcode,
+    operand: Option<Operand>,
+    span: Span,
+}
+
+/// Kinds of non-fatal diagnostics
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WarningKind {
+    #[error("Unstable undocumented opcode {0}, its result may differ between machines")]
+    UnstableOpcode(Opcode),
+}
+
+/// A non-fatal diagnostic with the location that caused it
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{span}: warning: {kind}")]
+pub struct Warning {
+    pub span: Span,
+    pub kind: WarningKind,
 }
 
 /// Assembler for converting AST to binary
@@ -50,26 +105,111 @@ pub struct Assembler {
     /// The resulting binary code
     binary: Vec<u8>,
     
+    /// Output of each statement in the final pass
+    listing: Vec<ListingEntry>,
+    
     /// Map of resolved labels to their addresses
     labels: HashMap<String, usize>,
     
-    /// Map of unresolved references to labels
-    unresolved_refs: Vec<(usize, String, bool)>, // Position, Label name, Is relative?
+    /// Address of the statement being assembled, the value of `*`
+    statement_pc: usize,
+    
+    /// Position of the statement being assembled in the AST
+    statement_index: usize,
     
-    /// Map of unresolved expression references
-    unresolved_expressions: Vec<(usize, String)>,
+    /// Address at each constant definition, so `*` in a constant refers to
+    /// where it is defined rather than where it is used
+    constant_pcs: HashMap<String, usize>,
+    
+    /// Values of constants at their definitions in the final pass, for the
+    /// symbol table
+    constant_values: HashMap<String, i64>,
     
     /// The origin address for the assembly
     origin: usize,
     
-    /// The current line number for error reporting
-    line_number: usize,
+    /// Origin of a source without an `.org` or BASIC stub
+    initial_origin: usize,
+    
+    /// Whether an `.org` has set the origin during the current layout pass
+    origin_set: bool,
+    
+    /// Whether this is the final (code generating) pass; unknown symbols
+    /// are only an error once every label has been laid out
+    final_pass: bool,
     
     /// Whether to enable verbose output
     verbose: bool,
     
+    /// Whether unstable undocumented opcodes are used without a warning
+    allow_unstable: bool,
+    
+    /// Whether branches out of reach are turned into a branch over a JMP
+    relax_branches: bool,
+    
+    /// Address of the first instruction after a BASIC stub in the previous
+    /// layout pass, the default SYS target of the stub
+    stub_entry: Option<usize>,
+    
+    /// Whether a BASIC stub has been laid out in the current pass but no
+    /// instruction after it yet
+    awaiting_entry: bool,
+    
+    /// Statements of branches that need the long form, once a branch is
+    /// long it stays long so the layout passes converge
+    long_branches: HashSet<usize>,
+    
+    /// Warnings raised during the final pass
+    warnings: Vec<Warning>,
+    
+    /// Instruction set at the start of the source, from the command line
+    initial_isa: Arc<dyn InstructionSet>,
+    
+    /// Instruction set in use, set by `.cpu`
+    isa: Arc<dyn InstructionSet>,
+    
+    /// Whether the 65816 accumulator is 16 bits wide, set by `.a8`/`.a16`
+    accumulator_wide: bool,
+    
+    /// Whether the 65816 index registers are 16 bits wide, set by `.i8`/`.i16`
+    index_wide: bool,
+    
+    /// Whether register widths are inferred at the start of the source
+    initial_smart: bool,
+    
+    /// Whether register widths follow REP/SEP instructions, set by `.smart`
+    smart: bool,
+    
     /// The AST being assembled (for accessing constants)
     ast: Option<Ast>,
+    
+    /// Constants currently being evaluated, to detect circular definitions
+    evaluating: Vec<String>,
+    
+    /// Set when an evaluation used a placeholder for an unknown symbol
+    unresolved_symbol: bool,
+    
+    /// Text encoding for strings and character literals, set by `.encoding`
+    encoding: Encoding,
+    
+    /// Character maps by name, the unnamed map is active by default
+    charmaps: HashMap<String, Charmap>,
+    
+    /// Name of the active character map
+    charmap: String,
+    
+    /// Names of the character maps saved by `.pushcharmap`
+    charmap_stack: Vec<String>,
+    
+    /// Parsed lines of the character map files loaded by `.charmapload`,
+    /// so each file is only read once per assembly
+    charmap_files: HashMap<PathBuf, Vec<(Span, Vec<DirectiveArg>)>>,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Assembler {
@@ -77,13 +217,37 @@ impl Assembler {
         Self {
             pc: 0,
             binary: Vec::new(),
+            listing: Vec::new(),
             labels: HashMap::new(),
-            unresolved_refs: Vec::new(),
-            unresolved_expressions: Vec::new(),
+            statement_pc: 0,
+            statement_index: 0,
+            constant_pcs: HashMap::new(),
+            constant_values: HashMap::new(),
             origin: 0x1000, // Default origin
-            line_number: 0,
+            initial_origin: 0x1000,
+            origin_set: false,
+            final_pass: false,
             verbose: false,
+            allow_unstable: false,
+            relax_branches: false,
+            stub_entry: None,
+            awaiting_entry: false,
+            long_branches: HashSet::new(),
+            warnings: Vec::new(),
+            initial_isa: Cpu::default().instruction_set(),
+            isa: Cpu::default().instruction_set(),
+            accumulator_wide: false,
+            index_wide: false,
+            initial_smart: false,
+            smart: false,
             ast: None,
+            evaluating: Vec::new(),
+            unresolved_symbol: false,
+            encoding: Encoding::default(),
+            charmaps: HashMap::new(),
+            charmap: String::new(),
+            charmap_stack: Vec::new(),
+            charmap_files: HashMap::new(),
         }
     }
     
@@ -93,289 +257,579 @@ impl Assembler {
         self
     }
     
-    /// Set the current line number for error reporting
-    pub fn set_line_number(&mut self, line: usize) {
-        self.line_number = line;
+    /// Accept unstable undocumented opcodes without warnings
+    pub fn allow_unstable(mut self, allow: bool) -> Self {
+        self.allow_unstable = allow;
+        self
+    }
+    
+    /// Assemble branches whose target is out of reach like long branches
+    pub fn relax_branches(mut self, relax: bool) -> Self {
+        self.relax_branches = relax;
+        self
     }
     
-    /// Create an error with the current line number
-    fn line_error(&self, message: String) -> AssemblerError {
-        AssemblerError::SourceLineError {
-            line: self.line_number,
-            message,
+    /// Select the processor at the start of the source
+    pub fn cpu(self, cpu: Cpu) -> Self {
+        self.instruction_set(cpu.instruction_set())
+    }
+    
+    /// Assemble for a processor without a built-in table, at the start of
+    /// the source
+    pub fn instruction_set(mut self, isa: Arc<dyn InstructionSet>) -> Self {
+        self.initial_isa = Arc::clone(&isa);
+        self.isa = isa;
+        self
+    }
+    
+    /// Infer 65816 register widths from REP/SEP instructions
+    pub fn smart(mut self, smart: bool) -> Self {
+        self.initial_smart = smart;
+        self.smart = smart;
+        self
+    }
+    
+    /// Warnings raised by the last assembly
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+    
+    /// The load address of the assembled program, as set by the first `.org`
+    pub fn origin(&self) -> usize {
+        self.origin
+    }
+    
+    /// Per-statement output of the last assembly, for generating listings
+    pub fn listing(&self) -> &[ListingEntry] {
+        &self.listing
+    }
+    
+    /// Labels and constants of the last assembly in source order, with their values
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let Some(ast) = &self.ast else {
+            return Vec::new();
+        };
+        
+        let mut symbols = Vec::new();
+        for statement in ast.statements() {
+            match statement {
+                Statement::Label(label) if label.is_anonymous() => {}
+                Statement::Label(label) => {
+                    let value = self.labels.get(&label.name).copied().unwrap_or_default();
+                    symbols.push(Symbol {
+                        name: label.name.clone(),
+                        kind: SymbolKind::Label,
+                        value: value as i64,
+                        span: label.span.clone(),
+                    });
+                }
+                Statement::Constant(constant) => {
+                    let value = self.constant_values.get(&constant.name).copied().unwrap_or_default();
+                    symbols.push(Symbol {
+                        name: constant.name.clone(),
+                        kind: SymbolKind::Constant,
+                        value,
+                        span: constant.span.clone(),
+                    });
+                }
+                _ => {}
+            }
         }
+        
+        symbols
+    }
+    
+    /// Assemble the AST into the contents of an output file of the given format
+    pub fn assemble_with_format(&mut self, ast: &Ast, format: OutputFormat) -> Result<Vec<u8>, AssemblerError> {
+        let binary = self.assemble(ast)?;
+        format.check_origin(self.origin).map_err(AssemblerError::ValueOutOfRange)?;
+        Ok(format.encode(self.origin, &binary))
     }
     
     /// Assemble the AST into binary
     pub fn assemble(&mut self, ast: &Ast) -> Result<Vec<u8>, AssemblerError> {
+        check_duplicate_symbols(ast)?;
+        
         // Save the AST for constant lookup
         self.ast = Some(ast.clone());
+        self.origin = self.initial_origin;
+        self.labels.clear();
+        self.constant_pcs.clear();
+        self.long_branches.clear();
+        self.stub_entry = None;
+        self.charmap_files.clear();
         
-        // First pass: resolve labels
-        self.resolve_labels(ast)?;
-        
-        // Second pass: generate code
-        self.generate_code(ast)?;
-        
-        // Third pass: resolve references
-        self.resolve_references()?;
-        
-        // If there are still unresolved references, try multiple passes
-        let mut pass_count = 1;
-        while !self.unresolved_refs.is_empty() && pass_count < 5 {
+        // Layout passes: instruction sizes depend on label values (zero page
+        // vs absolute) and vice versa, so repeat until the addresses settle
+        let mut pass = 1;
+        loop {
+            let previous_labels = self.labels.clone();
+            let previous_entry = self.stub_entry;
+            self.resolve_labels(ast)?;
+            
             if self.verbose {
-                println!("Pass {}: {} unresolved references remain", pass_count, self.unresolved_refs.len());
+                println!("Layout pass {}: {} labels", pass, self.labels.len());
+            }
+            
+            if pass > 1 && self.labels == previous_labels && self.stub_entry == previous_entry {
+                break;
+            }
+            if pass == MAX_LAYOUT_PASSES {
+                return Err(AssemblerError::ForwardReference(format!(
+                    "Label addresses did not converge after {} passes", pass
+                )));
             }
-            self.resolve_references()?;
-            pass_count += 1;
+            pass += 1;
         }
         
+        // Final pass: generate code
+        self.generate_code(ast)?;
+        
         Ok(self.binary.clone())
     }
     
-    /// First pass: Resolve labels
+    /// Layout pass: Resolve labels
+    /// 
+    /// Labels keep their addresses from the previous pass until they are
+    /// redefined, so forward references see the last known value.
     fn resolve_labels(&mut self, ast: &Ast) -> Result<(), AssemblerError> {
+        self.final_pass = false;
+        self.reset_directive_state();
+        self.origin_set = false;
+        self.awaiting_entry = false;
         self.pc = self.origin;
         
-        // Process directives first (for .org, etc.)
-        for directive in ast.directives() {
-            match directive.name.as_str() {
-                "org" => {
-                    let value = self.parse_value(&directive.value)?;
-                    self.origin = value;
-                    self.pc = value;
-                }
-                _ => {} // Other directives handled in second pass
-            }
+        // Number of bytes laid out so far, an .org before any output
+        // moves the origin of the whole program
+        let mut size = 0;
+        
+        for (index, statement) in ast.statements().iter().enumerate() {
+            self.statement_index = index;
+            self.layout_statement(statement, &mut size)
+                .map_err(|e| e.at(statement.span()))?;
         }
         
-        // Process labels
-        for (name, _label) in ast.labels() {
-            self.labels.insert(name.clone(), self.pc);
+        if self.verbose {
+            println!("Resolved labels: {:?}", self.labels);
         }
         
-        // Calculate PC for each instruction
-        for instruction in ast.instructions() {
-            let size = self.instruction_size(instruction)?;
-            self.pc += size;
+        Ok(())
+    }
+    
+    /// Assign addresses for a single statement during the first pass
+    fn layout_statement(&mut self, statement: &Statement, size: &mut usize) -> Result<(), AssemblerError> {
+        self.statement_pc = self.pc;
+        match statement {
+            Statement::Label(label) => {
+                self.labels.insert(label.name.clone(), self.pc);
+            }
+            Statement::Instruction(instruction) => {
+                if self.awaiting_entry {
+                    self.stub_entry = Some(self.pc);
+                    self.awaiting_entry = false;
+                }
+                let len = self.instruction_size(instruction)?;
+                self.pc += len;
+                *size += len;
+                let instruction = self.resolve(instruction)?;
+                self.track_register_widths(&instruction)?;
+            }
+            Statement::Directive(directive) if directive.name == "org" => {
+                let value = self.single_value(directive)?;
+                let value = check_range(value, self.origin_range(), "Origin")? as usize;
+                if *size == 0 {
+                    self.origin = value;
+                    self.origin_set = true;
+                } else {
+                    // Later origins are padded up to so the output stays contiguous
+                    *size += self.org_padding(value)?;
+                }
+                self.pc = value;
+            }
+            Statement::Directive(directive) if directive.name == "basicstub" && *size == 0 && !self.origin_set => {
+                // A stub at the start of the program without an explicit
+                // origin moves the program to the BASIC area
+                self.origin = BASIC_START;
+                self.pc = BASIC_START;
+                let len = self.directive_size(directive)?;
+                self.pc += len;
+                *size += len;
+                self.awaiting_entry = true;
+            }
+            Statement::Directive(directive) if STATE_DIRECTIVES.contains(&directive.name.as_str()) => {
+                // Instruction sizes and character literals in later
+                // addresses depend on these
+                self.process_directive(directive)?;
+            }
+            Statement::Directive(directive) => {
+                let len = self.directive_size(directive)?;
+                self.pc += len;
+                *size += len;
+                self.awaiting_entry |= directive.name == "basicstub";
+            }
+            Statement::Constant(constant) => {
+                // Constants are evaluated on demand
+                self.constant_pcs.insert(constant.name.clone(), self.pc);
+            }
         }
         
         Ok(())
     }
     
-    /// Second pass: Generate code
+    /// Final pass: Generate code
     fn generate_code(&mut self, ast: &Ast) -> Result<(), AssemblerError> {
+        self.final_pass = true;
+        self.reset_directive_state();
         self.pc = self.origin;
         self.binary = Vec::new();
+        self.listing = Vec::new();
+        self.warnings = Vec::new();
+        self.constant_values.clear();
         
-        // Process directives first
-        for directive in ast.directives() {
-            self.process_directive(directive)?;
-        }
-        
-        // Process instructions
-        let mut i = 0;
-        while i < ast.instructions().len() {
-            let instruction = &ast.instructions()[i];
-            let opcode_bytes = self.encode_instruction(instruction)?;
-            
-            for &byte in &opcode_bytes {
-                self.binary.push(byte);
-            }
-            
-            self.pc += opcode_bytes.len();
-            i += 1;
+        for (index, statement) in ast.statements().iter().enumerate() {
+            self.statement_index = index;
+            self.generate_statement(statement)
+                .map_err(|e| e.at(statement.span()))?;
         }
         
         Ok(())
     }
     
-    /// Third pass: Resolve references
-    fn resolve_references(&mut self) -> Result<(), AssemblerError> {
-        let mut remaining_refs = Vec::new();
+    /// Emit the code for a single statement during the second pass
+    fn generate_statement(&mut self, statement: &Statement) -> Result<(), AssemblerError> {
+        self.statement_pc = self.pc;
+        let address = self.pc;
+        let start = self.binary.len();
+        let mut cycles = None;
         
-        if self.verbose {
-            println!("Resolving references: {:?}", self.unresolved_refs);
-            println!("Binary size: {}", self.binary.len());
-        }
-        
-        for (pos, label, is_relative) in &self.unresolved_refs {
-            if let Some(&addr) = self.labels.get(label) {
-                // Skip references whose position is beyond the binary size
-                // This can happen if references were added during value parsing
-                // but the actual code generation never reached that point
-                if *pos >= self.binary.len() {
-                    if self.verbose {
-                        println!("Skipping reference to '{}' at ${:04X} (beyond binary size)", label, pos);
-                    }
-                    continue;
-                }
-                
-                if *is_relative {
-                    // Calculate relative address for branch instructions
-                    let current_pos = *pos + 2; // PC will be at the next instruction
-                    let rel_addr = (addr as isize - current_pos as isize) as i8;
+        match statement {
+            Statement::Instruction(instruction) => {
+                let mut total_cycles = 0;
+                for instruction in self.expand_branch(instruction)? {
+                    let addr_mode = self.addressing_mode(&instruction)?;
+                    let entry = self.get_opcode_entry(instruction.opcode, addr_mode)?;
+                    total_cycles += entry.cycles + self.wide_immediate(&entry) as u8;
                     
-                    // Check if the relative jump is in range (-128 to +127 bytes)
-                    if (addr as isize - current_pos as isize) > 127 || (addr as isize - current_pos as isize) < -128 {
-                        return Err(AssemblerError::ValueOutOfRange(
-                            format!("Branch to '{}' is too far (offset: {})", label, addr as isize - current_pos as isize)
-                        ));
-                    }
+                    let opcode_bytes = self.encode_instruction(&instruction)?;
+                    self.binary.extend_from_slice(&opcode_bytes);
+                    self.pc += opcode_bytes.len();
+                    self.track_register_widths(&instruction)?;
                     
-                    if *pos + 1 < self.binary.len() {
-                        self.binary[*pos + 1] = rel_addr as u8;
-                    }
-                } else {
-                    // Absolute address
-                    if *pos + 1 < self.binary.len() {
-                        self.binary[*pos + 1] = (addr & 0xFF) as u8;
+                    if entry.unstable && !self.allow_unstable {
+                        self.warnings.push(Warning {
+                            span: instruction.span.clone(),
+                            kind: WarningKind::UnstableOpcode(instruction.opcode),
+                        });
                     }
-                    
-                    // For 2-byte addresses
-                    if *pos + 2 < self.binary.len() {
-                        self.binary[*pos + 2] = ((addr >> 8) & 0xFF) as u8;
-                    }
-                }
-                
-                if self.verbose {
-                    println!("Resolved reference to '{}' at ${:04X} -> ${:04X}", label, *pos, addr);
                 }
-            } else {
-                // Still unresolved after multiple passes, keep for the next iteration
-                remaining_refs.push((*pos, label.clone(), *is_relative));
+                cycles = Some(total_cycles);
+            }
+            Statement::Directive(directive) => {
+                self.process_directive(directive)?;
+            }
+            Statement::Label(_) => {}
+            Statement::Constant(constant) => {
+                // Recorded with the encoding and character map in effect at
+                // the definition, as used by the code that follows
+                let value = self.resolve_symbol(&constant.name)?;
+                self.constant_values.insert(constant.name.clone(), value);
+                return Ok(());
             }
         }
         
-        // Update unresolved references for the next pass
-        self.unresolved_refs = remaining_refs;
+        // The padding emitted by .org is left out of the listing
+        let (address, bytes) = match statement {
+            Statement::Directive(directive) if directive.name == "org" => (self.pc, Vec::new()),
+            _ => (address, self.binary[start..].to_vec()),
+        };
+        self.listing.push(ListingEntry {
+            span: statement.span().clone(),
+            address,
+            bytes,
+            cycles,
+        });
         
-        // If there are still unresolved references after multiple passes, that's an error
-        if !self.unresolved_refs.is_empty() {
-            let missing_labels: Vec<String> = self.unresolved_refs
-                .iter()
-                .map(|(_, label, _)| label.clone())
-                .collect();
-            
-            return Err(AssemblerError::UnknownLabel(format!(
-                "Unresolved labels after multiple passes: {:?}", missing_labels
-            )));
+        Ok(())
+    }
+    
+    /// Calculate the size of an instruction in bytes
+    fn instruction_size(&mut self, instruction: &Instruction) -> Result<usize, AssemblerError> {
+        let mut size = 0;
+        for instruction in self.expand_branch(instruction)? {
+            size += self.resolved_size(&instruction)?;
+        }
+        Ok(size)
+    }
+    
+    /// Size of a single machine instruction in bytes
+    fn resolved_size(&mut self, instruction: &ResolvedInstruction) -> Result<usize, AssemblerError> {
+        let addr_mode = self.addressing_mode(instruction)?;
+        let entry = self.get_opcode_entry(instruction.opcode, addr_mode)?;
+        Ok(entry.size as usize + self.wide_immediate(&entry) as usize)
+    }
+    
+    /// Look up the mnemonic of an instruction in the instruction set in use
+    fn resolve(&self, instruction: &Instruction) -> Result<ResolvedInstruction, AssemblerError> {
+        let name = &instruction.mnemonic;
+        let Some(opcode) = self.isa.mnemonic(name) else {
+            return Err(match lookup_mnemonic(name) {
+                Some(opcode) => AssemblerError::UnsupportedInstruction(opcode, self.isa.name().to_string()),
+                None => AssemblerError::UnknownOpcode(name.to_uppercase()),
+            });
+        };
+        
+        // The three-byte `top` is never narrowed to zero page
+        let mut operand = instruction.operand.clone();
+        if self.isa.absolute_alias(name) {
+            operand = operand.map(|operand| operand.or_width(AddressWidth::Absolute));
+        }
+        Ok(ResolvedInstruction { opcode, operand, span: instruction.span.clone() })
+    }
+    
+    /// The instructions a statement is assembled as
+    /// 
+    /// Long branch pseudo instructions, and ordinary branches when relaxing,
+    /// stay a short branch while the target is in reach and become an
+    /// inverted branch over a JMP otherwise. The choice is made during
+    /// layout; a BRA becomes a plain JMP.
+    fn expand_branch(&mut self, instruction: &Instruction) -> Result<Vec<ResolvedInstruction>, AssemblerError> {
+        let instruction = self.resolve(instruction)?;
+        let pseudo = short_branch(instruction.opcode);
+        let branch = pseudo.unwrap_or(instruction.opcode);
+        let relaxable = (branch == Opcode::BRA || inverse_branch(branch).is_some())
+            && self.isa.supports(branch);
+        if pseudo.is_none() && !(self.relax_branches && relaxable) {
+            return Ok(vec![instruction]);
+        }
+        
+        let target = match &instruction.operand {
+            Some(Operand::Address(target, _)) => target,
+            _ if pseudo.is_some() => {
+                return Err(AssemblerError::InvalidAddressingMode(format!(
+                    "{} takes a branch target", instruction.opcode
+                )));
+            }
+            _ => return Ok(vec![instruction]),
+        };
+        let span = instruction.span.clone();
+        
+        if !self.final_pass && !self.long_branches.contains(&self.statement_index) {
+            self.unresolved_symbol = false;
+            let value = self.evaluate_expression(target)?;
+            let offset = value - (self.pc as i64 + SHORT_BRANCH_SIZE);
+            if !self.unresolved_symbol && !BRANCH_RANGE.contains(&offset) {
+                self.long_branches.insert(self.statement_index);
+            }
+        }
+        if !self.long_branches.contains(&self.statement_index) {
+            return Ok(vec![ResolvedInstruction { opcode: branch, operand: instruction.operand, span }]);
         }
         
+        let jump = ResolvedInstruction {
+            opcode: Opcode::JMP,
+            operand: Some(Operand::Address(target.clone(), AddressWidth::Auto)),
+            span: span.clone(),
+        };
+        let Some(inverse) = inverse_branch(branch) else {
+            return Ok(vec![jump]);
+        };
+        
+        // The inverted branch skips the JMP when the condition is false
+        let skip = self.pc as i64 + SHORT_BRANCH_SIZE + self.resolved_size(&jump)? as i64;
+        let skip = Operand::Address(Expr::Number(skip), AddressWidth::Auto);
+        Ok(vec![ResolvedInstruction { opcode: inverse, operand: Some(skip), span }, jump])
+    }
+    
+    /// Whether an instruction's immediate operand takes two bytes because
+    /// the 65816 register it is loaded into is 16 bits wide
+    fn wide_immediate(&self, entry: &OpcodeEntry) -> bool {
+        match entry.sized_by {
+            Some(Register::Accumulator) => self.accumulator_wide,
+            Some(Register::Index) => self.index_wide,
+            None => false,
+        }
+    }
+    
+    /// In smart mode, follow the M and X flags changed by REP and SEP so
+    /// the immediates after them get the right size
+    fn track_register_widths(&mut self, instruction: &ResolvedInstruction) -> Result<(), AssemblerError> {
+        if !self.smart || !matches!(instruction.opcode, Opcode::REP | Opcode::SEP) {
+            return Ok(());
+        }
+        let Some(Operand::Immediate(expr)) = &instruction.operand else {
+            return Ok(());
+        };
+        
+        let flags = self.evaluate_expression(expr)?;
+        let wide = instruction.opcode == Opcode::REP;
+        if flags & FLAG_M != 0 {
+            self.accumulator_wide = wide;
+        }
+        if flags & FLAG_X != 0 {
+            self.index_wide = wide;
+        }
         Ok(())
     }
     
-    /// Calculate the size of an instruction in bytes
-    fn instruction_size(&self, instruction: &Instruction) -> Result<usize, AssemblerError> {
-        if let Some(operand) = &instruction.operand {
-            let addr_mode = operand.get_addressing_mode(instruction.opcode);
-            
-            match addr_mode {
-                AddressingMode::Implied | AddressingMode::Accumulator => Ok(1),
-                AddressingMode::Immediate | AddressingMode::ZeroPage | 
-                AddressingMode::ZeroPageX | AddressingMode::ZeroPageY |
-                AddressingMode::Relative | AddressingMode::IndexedIndirect |
-                AddressingMode::IndirectIndexed => Ok(2),
-                AddressingMode::Absolute | AddressingMode::AbsoluteX |
-                AddressingMode::AbsoluteY | AddressingMode::Indirect => Ok(3),
+    /// Select the addressing mode for an instruction
+    /// 
+    /// The zero page form is used when the operand's value is known and
+    /// fits into a byte; operands referring to symbols that have not been
+    /// resolved yet conservatively use the absolute form.
+    fn addressing_mode(&mut self, instruction: &ResolvedInstruction) -> Result<AddressingMode, AssemblerError> {
+        let Some(operand) = &instruction.operand else {
+            // No operand - implied addressing, or the accumulator for
+            // shifts and rotates written without `A`
+            if self.get_opcode_entry(instruction.opcode, AddressingMode::Implied).is_err()
+                && self.get_opcode_entry(instruction.opcode, AddressingMode::Accumulator).is_ok() {
+                return Ok(AddressingMode::Accumulator);
             }
-        } else {
-            // No operand - implied addressing
-            Ok(1)
+            return Ok(AddressingMode::Implied);
+        };
+        
+        let opcode = instruction.opcode;
+        let addr_mode = operand.get_addressing_mode(opcode, self.isa.as_ref());
+        if operand.width() != AddressWidth::Auto {
+            return Ok(addr_mode);
+        }
+        let Some(value) = operand.value() else {
+            return Ok(addr_mode);
+        };
+        let zp_mode = addr_mode.zero_page().filter(|&mode| self.get_opcode_entry(opcode, mode).is_ok());
+        let long_mode = addr_mode.long().filter(|&mode| self.get_opcode_entry(opcode, mode).is_ok());
+        
+        // Instructions such as JSL only come in the long form
+        if let Some(long_mode) = long_mode.filter(|_| self.get_opcode_entry(opcode, addr_mode).is_err()) {
+            return Ok(long_mode);
+        }
+        if zp_mode.is_none() && long_mode.is_none() {
+            return Ok(addr_mode);
+        }
+        
+        self.unresolved_symbol = false;
+        let value = self.evaluate_expression(value)?;
+        if self.unresolved_symbol {
+            return Ok(addr_mode);
+        }
+        let value = self.program_bank_address(opcode, addr_mode, value);
+        match (zp_mode, long_mode) {
+            (Some(zp_mode), _) if ZERO_PAGE_RANGE.contains(&value) => Ok(zp_mode),
+            (_, Some(long_mode)) if !ADDRESS_RANGE.contains(&value) => Ok(long_mode),
+            _ => Ok(addr_mode),
+        }
+    }
+    
+    /// Calculate the size of a directive's output in bytes
+    fn directive_size(&mut self, directive: &Directive) -> Result<usize, AssemblerError> {
+        match directive.name.as_str() {
+            "org" => Ok(0),
+            name if STATE_DIRECTIVES.contains(&name) => Ok(0),
+            "byte" | "db" | "text" | "ascii" | "petscii" | "scr" => {
+                Ok(directive.args
+                    .iter()
+                    .map(|arg| match arg {
+                        DirectiveArg::String(text) => text.len(),
+                        DirectiveArg::Expr(_) => 1,
+                    })
+                    .sum())
+            },
+            "word" | "dw" => Ok(2 * directive.args.len()),
+            "basicstub" => Ok(self.basic_stub(directive)?.len()),
+            other => Err(AssemblerError::UnknownDirective(other.to_string()))
         }
     }
     
     /// Encode an instruction to bytes
-    fn encode_instruction(&mut self, instruction: &Instruction) -> Result<Vec<u8>, AssemblerError> {
-        let addr_mode = if let Some(operand) = &instruction.operand {
-            operand.get_addressing_mode(instruction.opcode)
-        } else {
-            AddressingMode::Implied
-        };
+    fn encode_instruction(&mut self, instruction: &ResolvedInstruction) -> Result<Vec<u8>, AssemblerError> {
+        let addr_mode = self.addressing_mode(instruction)?;
         
-        let opcode_byte = self.get_opcode_byte(instruction.opcode, addr_mode)?;
-        let mut bytes = vec![opcode_byte];
+        let entry = self.get_opcode_entry(instruction.opcode, addr_mode)?;
+        let mut bytes = vec![entry.byte];
         
         if let Some(operand) = &instruction.operand {
+            let value = match operand.value() {
+                Some(expr) => self.evaluate_expression(expr)?,
+                None => 0,
+            };
+            let value = self.program_bank_address(instruction.opcode, addr_mode, value);
+            
             match addr_mode {
                 AddressingMode::Implied | AddressingMode::Accumulator => {
                     // No operand bytes
                 }
+                AddressingMode::Immediate if self.wide_immediate(&entry) => {
+                    let value = check_range(value, WORD_RANGE, "Immediate value")?;
+                    bytes.push((value & 0xFF) as u8);
+                    bytes.push(((value >> 8) & 0xFF) as u8);
+                }
                 AddressingMode::Immediate => {
-                    let operand_str = operand.to_string();
-                    let value_str = operand_str.trim_start_matches('#');
-                    let value = self.parse_value(value_str)?;
-                    if value > 0xFF {
-                        return Err(self.line_error(format!(
-                            "Immediate value out of range: {} > 0xFF", value
-                        )));
-                    }
+                    let value = check_range(value, BYTE_RANGE, "Immediate value")?;
                     bytes.push((value & 0xFF) as u8);
                 }
-                AddressingMode::ZeroPage | AddressingMode::ZeroPageX | AddressingMode::ZeroPageY => {
-                    let operand_str = operand.to_string();
-                    let value_str = operand_str.split(',').next().unwrap_or("");
-                    let value = self.parse_value(value_str)?;
-                    if value > 0xFF {
-                        return Err(self.line_error(format!(
-                            "Zero page address out of range: {} > 0xFF", value
-                        )));
-                    }
+                AddressingMode::ZeroPage | AddressingMode::ZeroPageX | AddressingMode::ZeroPageY
+                    if operand.width() == AddressWidth::ZeroPage => {
+                    let value = check_range(value, ZERO_PAGE_RANGE, "Forced zero page address")?;
+                    bytes.push((value & 0xFF) as u8);
+                }
+                AddressingMode::ZeroPage | AddressingMode::ZeroPageX | AddressingMode::ZeroPageY |
+                AddressingMode::IndexedIndirect | AddressingMode::IndirectIndexed |
+                AddressingMode::ZeroPageIndirect | AddressingMode::ZeroPageIndirectLong |
+                AddressingMode::ZeroPageIndirectLongIndexed => {
+                    let value = check_range(value, ZERO_PAGE_RANGE, "Zero page address")?;
                     bytes.push((value & 0xFF) as u8);
                 }
                 AddressingMode::Absolute | AddressingMode::AbsoluteX | AddressingMode::AbsoluteY => {
-                    let operand_str = operand.to_string();
-                    let value_str = operand_str.split(',').next().unwrap_or("");
-                    let value = self.parse_value(value_str)?;
-                    if value > 0xFFFF {
-                        return Err(self.line_error(format!(
-                            "Absolute address out of range: {} > 0xFFFF", value
-                        )));
-                    }
+                    let value = check_range(value, ADDRESS_RANGE, "Absolute address")?;
                     bytes.push((value & 0xFF) as u8);
                     bytes.push(((value >> 8) & 0xFF) as u8);
                 }
-                AddressingMode::Indirect => {
-                    let operand_str = operand.to_string();
-                    let value_str = operand_str.trim_start_matches('(').trim_end_matches(')');
-                    let value = self.parse_value(value_str)?;
-                    if value > 0xFFFF {
-                        return Err(self.line_error(format!(
-                            "Indirect address out of range: {} > 0xFFFF", value
-                        )));
-                    }
+                AddressingMode::AbsoluteLong | AddressingMode::AbsoluteLongX => {
+                    let value = check_range(value, LONG_ADDRESS_RANGE, "Long address")?;
                     bytes.push((value & 0xFF) as u8);
                     bytes.push(((value >> 8) & 0xFF) as u8);
+                    bytes.push(((value >> 16) & 0xFF) as u8);
                 }
-                AddressingMode::IndexedIndirect | AddressingMode::IndirectIndexed => {
-                    let operand_str = operand.to_string();
-                    let value_str = if operand_str.contains(',') {
-                        operand_str
-                            .trim_start_matches('(')
-                            .split(',')
-                            .next()
-                            .unwrap_or("")
-                    } else {
-                        operand_str
-                            .trim_start_matches('(')
-                            .trim_end_matches(")")
-                    };
-                    
-                    let value = self.parse_value(value_str)?;
-                    if value > 0xFF {
-                        return Err(self.line_error(format!(
-                            "Zero page address out of range: {} > 0xFF", value
-                        )));
-                    }
+                AddressingMode::StackRelative | AddressingMode::StackRelativeIndirectIndexed => {
+                    let value = check_range(value, ZERO_PAGE_RANGE, "Stack offset")?;
+                    bytes.push((value & 0xFF) as u8);
+                }
+                AddressingMode::Indirect | AddressingMode::AbsoluteIndexedIndirect |
+                AddressingMode::AbsoluteIndirectLong => {
+                    let value = check_range(value, ADDRESS_RANGE, "Indirect address")?;
                     bytes.push((value & 0xFF) as u8);
+                    bytes.push(((value >> 8) & 0xFF) as u8);
                 }
                 AddressingMode::Relative => {
-                    // For branch instructions, relative addressing
-                    // We'll store the label name and resolve it in the third pass
-                    let label = operand.to_string();
-                    // Store position, label, and flag that this is a relative jump
-                    self.unresolved_refs.push((self.pc, label, true));
-                    bytes.push(0); // Placeholder
+                    let offset = self.branch_offset(value, &entry, BRANCH_RANGE, operand)?;
+                    bytes.push(offset as i8 as u8);
+                }
+                AddressingMode::RelativeLong => {
+                    let offset = self.branch_offset(value, &entry, LONG_BRANCH_RANGE, operand)?;
+                    bytes.push((offset & 0xFF) as u8);
+                    bytes.push(((offset >> 8) & 0xFF) as u8);
+                }
+                AddressingMode::BlockMove => {
+                    // Written source first, encoded destination first
+                    let source = check_range(value, ZERO_PAGE_RANGE, "Source bank")?;
+                    let destination = match operand.second() {
+                        Some(expr) => self.evaluate_expression(expr)?,
+                        None => 0,
+                    };
+                    let destination = check_range(destination, ZERO_PAGE_RANGE, "Destination bank")?;
+                    bytes.push((destination & 0xFF) as u8);
+                    bytes.push((source & 0xFF) as u8);
+                }
+                AddressingMode::ZeroPageRelative => {
+                    // Bit branches test a zero page byte before the offset
+                    let value = check_range(value, ZERO_PAGE_RANGE, "Zero page address")?;
+                    bytes.push((value & 0xFF) as u8);
+                    
+                    let target = match operand.second() {
+                        Some(expr) => self.evaluate_expression(expr)?,
+                        None => 0,
+                    };
+                    let offset = self.branch_offset(target, &entry, BRANCH_RANGE, operand)?;
+                    bytes.push(offset as i8 as u8);
                 }
             }
         }
@@ -383,149 +837,610 @@ impl Assembler {
         Ok(bytes)
     }
     
-    /// Get the opcode byte for a given opcode and addressing mode
-    fn get_opcode_byte(&self, opcode: Opcode, addr_mode: AddressingMode) -> Result<u8, AssemblerError> {
-        // Use the complete opcode lookup table
-        static OPCODE_TABLE: once_cell::sync::Lazy<HashMap<(Opcode, AddressingMode), self::opcodes::OpcodeEntry>> = 
-            once_cell::sync::Lazy::new(|| build_opcode_table());
-        
-        if let Some(entry) = OPCODE_TABLE.get(&(opcode, addr_mode)) {
-            Ok(entry.byte)
+    /// Offset of a branch target from the end of the branch instruction,
+    /// checked against the range of the offset in the final pass
+    fn branch_offset(&self, target: i64, entry: &OpcodeEntry, range: RangeInclusive<i64>, operand: &Operand) -> Result<i64, AssemblerError> {
+        let offset = target - (self.pc as i64 + entry.size as i64);
+        if self.final_pass && !range.contains(&offset) {
+            return Err(AssemblerError::ValueOutOfRange(
+                format!("Branch to '{}' is too far (offset: {})", operand, offset)
+            ));
+        }
+        Ok(offset)
+    }
+    
+    /// Get the opcode table entry for a given opcode and addressing mode
+    fn get_opcode_entry(&self, opcode: Opcode, addr_mode: AddressingMode) -> Result<OpcodeEntry, AssemblerError> {
+        if let Some(entry) = self.isa.entry(opcode, addr_mode) {
+            Ok(entry)
+        } else if !self.isa.supports(opcode) {
+            Err(AssemblerError::UnsupportedInstruction(opcode, self.isa.name().to_string()))
         } else {
             Err(AssemblerError::InvalidAddressingMode(format!(
-                "Invalid addressing mode {:?} for opcode {:?}", addr_mode, opcode
+                "Invalid addressing mode {:?} for opcode {}", addr_mode, opcode
             )))
         }
     }
     
-    /// Parse a value (number, label, constant, etc.)
-    fn parse_value(&mut self, value: &str) -> Result<usize, AssemblerError> {
-        // Check if it's a string literal
-        if value.starts_with('"') && value.ends_with('"') {
-            // For string literals, we'll just return the ASCII value of the first character
-            let text = &value[1..value.len()-1];
-            if !text.is_empty() {
-                return Ok(text.bytes().next().unwrap() as usize);
-            } else {
-                return Err(AssemblerError::Parse("Empty string literal".to_string()));
-            }
+    /// Evaluate a directive argument that has to be a number
+    fn evaluate_arg(&mut self, arg: &DirectiveArg) -> Result<i64, AssemblerError> {
+        match arg {
+            DirectiveArg::Expr(expr) => self.evaluate_expression(expr),
+            DirectiveArg::String(text) => Err(AssemblerError::InvalidExpression(format!(
+                "Expected a value, got string \"{}\"", text
+            ))),
         }
-        
-        // First check if it's a numeric literal
-        if value.starts_with('$') {
-            // Hexadecimal
-            let hex_str = &value[1..];
-            return usize::from_str_radix(hex_str, 16).map_err(|_| {
-                AssemblerError::Parse(format!("Invalid hexadecimal value: {}", value))
-            });
-        } else if value.starts_with('%') {
-            // Binary
-            let bin_str = &value[1..];
-            return usize::from_str_radix(bin_str, 2).map_err(|_| {
-                AssemblerError::Parse(format!("Invalid binary value: {}", value))
-            });
-        } else if value.chars().all(|c| c.is_digit(10)) {
-            // Decimal
-            return value.parse::<usize>().map_err(|_| {
-                AssemblerError::Parse(format!("Invalid decimal value: {}", value))
-            });
+    }
+    
+    /// Evaluate the only argument of a directive such as `.org`
+    fn single_value(&mut self, directive: &Directive) -> Result<i64, AssemblerError> {
+        match directive.args.as_slice() {
+            [arg] => self.evaluate_arg(arg),
+            _ => Err(AssemblerError::InvalidExpression(format!(
+                ".{} takes exactly one argument, got {}", directive.name, directive.args.len()
+            ))),
         }
-        
+    }
+    
+    /// Byte value of a character in the active character map or text encoding
+    fn encode_char(&self, c: char) -> Result<u8, AssemblerError> {
+        self.charmaps.get(&self.charmap)
+            .and_then(|charmap| charmap.get(c))
+            .or_else(|| self.encoding.encode_char(c))
+            .ok_or(AssemblerError::UnmappedCharacter(c, self.encoding))
+    }
+    
+    /// Forget the CPU, register widths, encodings and character maps set up
+    /// by a previous pass
+    fn reset_directive_state(&mut self) {
+        self.isa = Arc::clone(&self.initial_isa);
+        self.accumulator_wide = false;
+        self.index_wide = false;
+        self.smart = self.initial_smart;
+        self.encoding = Encoding::default();
+        self.charmaps.clear();
+        self.charmap = String::new();
+        self.charmap_stack.clear();
+    }
+    
+    /// Jump and call targets in the bank of the program counter as 16-bit
+    /// addresses, the 65816 takes their bank from the program bank register
+    fn program_bank_address(&self, opcode: Opcode, mode: AddressingMode, value: i64) -> i64 {
+        let in_program_bank = matches!(opcode, Opcode::JMP | Opcode::JSR)
+            && matches!(mode, AddressingMode::Absolute | AddressingMode::AbsoluteIndexedIndirect)
+            && value >> 16 == self.pc as i64 >> 16;
+        if in_program_bank { value & 0xFFFF } else { value }
+    }
+    
+    /// Addresses code can be placed at, any bank with long addressing
+    fn origin_range(&self) -> RangeInclusive<i64> {
+        if self.isa.long_addressing() {
+            LONG_ADDRESS_RANGE
+        } else {
+            ADDRESS_RANGE
+        }
+    }
+    
+    /// A forward anonymous label reference as written, such as `++`, from
+    /// the number of `+` labels before the statement or constant using it
+    fn anonymous_reference(&self, name: &str) -> String {
+        let (Some(ast), Ok(index)) = (&self.ast, name[1..].parse::<usize>()) else {
+            return name.to_string();
+        };
+        let statements = ast.statements();
+        let end = match self.evaluating.last() {
+            Some(constant) => statements
+                .iter()
+                .position(|s| matches!(s, Statement::Constant(c) if &c.name == constant))
+                .unwrap_or(statements.len()),
+            None => self.statement_index,
+        };
+        let defined = statements[..end]
+            .iter()
+            .filter(|s| matches!(s, Statement::Label(label) if label.name.starts_with('+')))
+            .count();
+        name[..1].repeat((index + 1).saturating_sub(defined))
+    }
+    
+    /// Select the text encoding for the following statements (`.encoding petscii`)
+    fn set_encoding(&mut self, directive: &Directive) -> Result<(), AssemblerError> {
+        let name = name_arg(directive, "the name of an encoding")?;
+        self.encoding = name.parse().map_err(AssemblerError::InvalidExpression)?;
+        Ok(())
+    }
+    
+    /// Evaluate an expression
+    fn evaluate_expression(&mut self, expr: &Expr) -> Result<i64, AssemblerError> {
+        match expr {
+            Expr::Number(n) => Ok(*n),
+            Expr::Char(c) => Ok(self.encode_char(*c)? as i64),
+            Expr::Symbol(name) => self.resolve_symbol(name),
+            Expr::CurrentPc => Ok(self.statement_pc as i64),
+            Expr::Unary(op, operand) => {
+                let value = self.evaluate_expression(operand)?;
+                Ok(match op {
+                    UnaryOp::Negate => value.wrapping_neg(),
+                    UnaryOp::BitNot => !value,
+                    UnaryOp::LogicalNot => (value == 0) as i64,
+                    UnaryOp::LowByte => value & 0xFF,
+                    UnaryOp::HighByte => (value >> 8) & 0xFF,
+                    UnaryOp::BankByte => (value >> 16) & 0xFF,
+                })
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.evaluate_expression(lhs)?;
+                let rhs = self.evaluate_expression(rhs)?;
+                match op {
+                    BinaryOp::Div | BinaryOp::Mod if rhs == 0 => {
+                        // Operands may still be placeholders before the final pass
+                        if self.final_pass {
+                            Err(AssemblerError::InvalidExpression(format!("Division by zero: {}", expr)))
+                        } else {
+                            Ok(0)
+                        }
+                    }
+                    BinaryOp::Add => Ok(lhs.wrapping_add(rhs)),
+                    BinaryOp::Sub => Ok(lhs.wrapping_sub(rhs)),
+                    BinaryOp::Mul => Ok(lhs.wrapping_mul(rhs)),
+                    BinaryOp::Div => Ok(lhs.wrapping_div(rhs)),
+                    BinaryOp::Mod => Ok(lhs.wrapping_rem(rhs)),
+                    BinaryOp::BitAnd => Ok(lhs & rhs),
+                    BinaryOp::BitOr => Ok(lhs | rhs),
+                    BinaryOp::BitXor => Ok(lhs ^ rhs),
+                    BinaryOp::Shl | BinaryOp::Shr => {
+                        let shifted = u32::try_from(rhs).ok().and_then(|amount| match op {
+                            BinaryOp::Shl => lhs.checked_shl(amount),
+                            _ => lhs.checked_shr(amount),
+                        });
+                        match shifted {
+                            Some(value) => Ok(value),
+                            None if self.final_pass => Err(AssemblerError::InvalidExpression(
+                                format!("Shift amount out of range: {}", expr))),
+                            None => Ok(0),
+                        }
+                    }
+                    BinaryOp::Eq => Ok((lhs == rhs) as i64),
+                    BinaryOp::Ne => Ok((lhs != rhs) as i64),
+                    BinaryOp::Lt => Ok((lhs < rhs) as i64),
+                    BinaryOp::Le => Ok((lhs <= rhs) as i64),
+                    BinaryOp::Gt => Ok((lhs > rhs) as i64),
+                    BinaryOp::Ge => Ok((lhs >= rhs) as i64),
+                    BinaryOp::LogicalAnd => Ok((lhs != 0 && rhs != 0) as i64),
+                    BinaryOp::LogicalOr => Ok((lhs != 0 || rhs != 0) as i64),
+                }
+            }
+        }
+    }
+    
+    /// Resolve a symbol to the address of a label or the value of a constant
+    fn resolve_symbol(&mut self, name: &str) -> Result<i64, AssemblerError> {
         // Check if it's a label
-        if let Some(&addr) = self.labels.get(value) {
-            return Ok(addr);
+        if let Some(&addr) = self.labels.get(name) {
+            return Ok(addr as i64);
         }
         
         // Otherwise check for constants
         let mut constant_value = None;
         if let Some(ref ast) = self.ast {
-            constant_value = ast.constants().get(value).cloned();
+            constant_value = ast.constant(name).map(|c| c.value.clone());
         }
         
         if let Some(const_val) = constant_value {
-            // Call parse_value on the constant value
-            return self.parse_value(&const_val);
+            if self.evaluating.iter().any(|n| n == name) {
+                return Err(AssemblerError::InvalidExpression(format!(
+                    "Circular definition of constant: {}", name
+                )));
+            }
+            self.evaluating.push(name.to_string());
+            let use_pc = self.statement_pc;
+            if let Some(&pc) = self.constant_pcs.get(name) {
+                self.statement_pc = pc;
+            }
+            let value = self.evaluate_expression(&const_val);
+            self.statement_pc = use_pc;
+            self.evaluating.pop();
+            return value;
         }
-            
-        // If we get here, it's likely a forward reference
-        self.unresolved_refs.push((self.pc, value.to_string(), false));
+        
+        // Labels defined further down are not known before the first pass
+        // has completed, so only the final pass may reject them
+        if self.final_pass && is_anonymous_label(name) {
+            return Err(AssemblerError::UnknownLabel(format!(
+                "No anonymous label for '{}' after this line", self.anonymous_reference(name)
+            )));
+        }
+        if self.final_pass {
+            return Err(AssemblerError::UnknownLabel(name.to_string()));
+        }
+        self.unresolved_symbol = true;
         Ok(0) // Placeholder
     }
     
-    /// Evaluate an expression (for unresolved expressions)
-    fn evaluate_expression(&self, expr: &str) -> Result<usize, AssemblerError> {
-        // For now, just a placeholder - would need to implement expression parsing and evaluation
-        Err(AssemblerError::InvalidExpression(format!("Invalid expression: {}", expr)))
-    }
-    
     /// Process a directive
-    fn process_directive(&mut self, directive: &crate::ast::Directive) -> Result<(), AssemblerError> {
+    fn process_directive(&mut self, directive: &Directive) -> Result<(), AssemblerError> {
         match directive.name.as_str() {
             "org" => {
-                let value = self.parse_value(&directive.value)?;
-                self.origin = value;
+                let value = self.single_value(directive)?;
+                let value = check_range(value, self.origin_range(), "Origin")? as usize;
+                if !self.binary.is_empty() {
+                    let padding = self.org_padding(value)?;
+                    self.binary.resize(self.binary.len() + padding, 0);
+                }
                 self.pc = value;
                 Ok(())
             },
-            "byte" | "db" => {
-                // Handle byte directive (.byte 1, 2, 3, 4)
-                if directive.value.starts_with('"') && directive.value.ends_with('"') {
-                    // Handle string literals in byte directives
-                    let text = &directive.value[1..directive.value.len()-1];
-                    for c in text.bytes() {
-                        self.binary.push(c);
-                        self.pc += 1;
-                    }
-                    Ok(())
-                } else {
-                    // Handle numeric byte values
-                    let values: Vec<&str> = directive.value.split(',').map(|v| v.trim()).collect();
-                    for value_str in values {
-                        let value = self.parse_value(value_str)?;
-                        if value > 0xFF {
-                            return Err(self.line_error(format!(
-                                "Byte value out of range: {} > 0xFF", value
-                            )));
-                        }
-                        self.binary.push((value & 0xFF) as u8);
-                        self.pc += 1;
-                    }
-                    Ok(())
+            "cpu" => {
+                // Switch the instruction set (.cpu 65c02)
+                let name = name_arg(directive, "the name of a CPU")?;
+                let cpu: Cpu = name.parse().map_err(AssemblerError::InvalidExpression)?;
+                self.isa = cpu.instruction_set();
+                Ok(())
+            }
+            "a8" | "a16" | "i8" | "i16" => {
+                // Declare the 65816 register widths (.a16)
+                if !directive.args.is_empty() {
+                    return Err(AssemblerError::InvalidExpression(format!(".{} takes no arguments", directive.name)));
+                }
+                match directive.name.as_str() {
+                    "a8" => self.accumulator_wide = false,
+                    "a16" => self.accumulator_wide = true,
+                    "i8" => self.index_wide = false,
+                    _ => self.index_wide = true,
                 }
+                Ok(())
+            }
+            "smart" => {
+                // Infer register widths from REP/SEP (.smart, .smart off)
+                self.smart = match directive.args.as_slice() {
+                    [] => true,
+                    _ => match name_arg(directive, "on or off")?.to_lowercase().as_str() {
+                        "on" => true,
+                        "off" => false,
+                        other => return Err(AssemblerError::InvalidExpression(format!(
+                            ".smart takes on or off, got {}", other
+                        ))),
+                    },
+                };
+                Ok(())
+            }
+            "encoding" => self.set_encoding(directive),
+            "charmap" => self.define_charmap(&directive.args),
+            "charmapload" => self.load_charmap(directive),
+            "pushcharmap" => {
+                // Switch to a named character map (.pushcharmap "font")
+                let name = name_arg(directive, "the name of a character map")?;
+                self.charmaps.entry(name.clone()).or_default();
+                let previous = std::mem::replace(&mut self.charmap, name);
+                self.charmap_stack.push(previous);
+                Ok(())
+            },
+            "popcharmap" => {
+                self.charmap = self.charmap_stack.pop().ok_or_else(|| AssemblerError::InvalidExpression(
+                    ".popcharmap without a matching .pushcharmap".to_string()
+                ))?;
+                Ok(())
+            },
+            "byte" | "db" | "text" => {
+                // Handle byte and text directives (.byte "HI", $0d, <msg, >msg)
+                self.emit_data(directive)
+            },
+            "ascii" | "petscii" | "scr" => {
+                // Text directives with a fixed encoding, which also applies
+                // to character literals in their arguments
+                let encoding = match directive.name.as_str() {
+                    "ascii" => Encoding::Ascii,
+                    "petscii" => Encoding::Petscii,
+                    _ => Encoding::Screen,
+                };
+                let active = std::mem::replace(&mut self.encoding, encoding);
+                let result = self.emit_data(directive);
+                self.encoding = active;
+                result
             },
             "word" | "dw" => {
                 // Handle word directive (.word $1000, $2000)
-                let values: Vec<&str> = directive.value.split(',').map(|v| v.trim()).collect();
-                for value_str in values {
-                    let value = self.parse_value(value_str)?;
-                    if value > 0xFFFF {
-                        return Err(self.line_error(format!(
-                            "Word value out of range: {} > 0xFFFF", value
-                        )));
-                    }
+                for arg in &directive.args {
+                    let value = self.evaluate_arg(arg)?;
+                    let value = check_range(value, WORD_RANGE, "Word value")?;
                     self.binary.push((value & 0xFF) as u8);
                     self.binary.push(((value >> 8) & 0xFF) as u8);
                     self.pc += 2;
                 }
                 Ok(())
             },
-            "text" | "ascii" => {
-                // Handle text directive (.text "Hello, world!")
-                let text = if directive.value.starts_with('"') && directive.value.ends_with('"') {
-                    &directive.value[1..directive.value.len()-1]
-                } else {
-                    &directive.value
-                };
-                
-                for c in text.bytes() {
-                    self.binary.push(c);
-                    self.pc += 1;
-                }
+            "basicstub" => {
+                // Handle BASIC stub directive (.basicstub [line], [entry])
+                let stub = self.basic_stub(directive)?;
+                self.binary.extend_from_slice(&stub);
+                self.pc += stub.len();
                 Ok(())
             },
             other => Err(AssemblerError::UnknownDirective(other.to_string()))
         }
     }
+    
+    /// Remap a character (`.charmap 'A', $01`) or a range of characters
+    /// (`.charmap 'A', 'Z', $01`) in the active character map
+    fn define_charmap(&mut self, args: &[DirectiveArg]) -> Result<(), AssemblerError> {
+        let (first, last, value) = match args {
+            [first, value] => (first, first, value),
+            [first, last, value] => (first, last, value),
+            _ => {
+                return Err(AssemblerError::InvalidExpression(
+                    ".charmap takes a character or a range of characters and a value".to_string()
+                ));
+            }
+        };
+        
+        let first = self.charmap_char(first)?;
+        let last = self.charmap_char(last)?;
+        if last < first {
+            return Err(AssemblerError::InvalidExpression(format!(
+                "Empty character range {:?} to {:?}", first, last
+            )));
+        }
+        let value = check_range(self.evaluate_arg(value)?, 0..=0xFF, "Character map value")?;
+        check_range(value + (last as i64 - first as i64), 0..=0xFF, "Character map range end")?;
+        
+        let charmap = self.charmaps.entry(self.charmap.clone()).or_default();
+        for (offset, c) in (first..=last).enumerate() {
+            charmap.insert(c, (value + offset as i64) as u8);
+        }
+        Ok(())
+    }
+    
+    /// Character being remapped, given as a literal or a numeric code point
+    fn charmap_char(&mut self, arg: &DirectiveArg) -> Result<char, AssemblerError> {
+        match arg {
+            DirectiveArg::Expr(Expr::Char(c)) => Ok(*c),
+            DirectiveArg::String(text) => match text.chars.as_slice() {
+                [TextChar::Char(c)] => Ok(*c),
+                _ => Err(AssemblerError::InvalidExpression(format!(
+                    "Expected a single character, got string \"{}\"", text
+                ))),
+            },
+            DirectiveArg::Expr(expr) => {
+                let code = self.evaluate_expression(expr)?;
+                u32::try_from(code).ok().and_then(char::from_u32).ok_or_else(|| {
+                    AssemblerError::ValueOutOfRange(format!("Invalid character code: {}", code))
+                })
+            }
+        }
+    }
+    
+    /// Apply a character map file to the active map (`.charmapload "font.map"`)
+    /// 
+    /// Each line holds the arguments of a `.charmap` directive, the path is
+    /// relative to the including source file.
+    fn load_charmap(&mut self, directive: &Directive) -> Result<(), AssemblerError> {
+        let file = name_arg(directive, "a file name")?;
+        let path = Path::new(&directive.span.file)
+            .parent()
+            .map(|dir| dir.join(&file))
+            .unwrap_or_else(|| file.into());
+        
+        if !self.charmap_files.contains_key(&path) {
+            let lines = read_charmap_file(&path)?;
+            self.charmap_files.insert(path.clone(), lines);
+        }
+        for (span, args) in self.charmap_files[&path].clone() {
+            self.define_charmap(&args).map_err(|e| e.at(&span))?;
+        }
+        Ok(())
+    }
+    
+    /// Emit strings and byte values of a data directive in the active encoding
+    fn emit_data(&mut self, directive: &Directive) -> Result<(), AssemblerError> {
+        for arg in &directive.args {
+            match arg {
+                DirectiveArg::String(text) => {
+                    for c in &text.chars {
+                        let byte = match *c {
+                            TextChar::Char(c) => self.encode_char(c)?,
+                            TextChar::Control(code) if self.encoding.has_control_codes() => code,
+                            TextChar::Control(code) => {
+                                return Err(AssemblerError::UnmappedControlCode(code, self.encoding));
+                            }
+                            TextChar::Byte(byte) => byte,
+                        };
+                        self.binary.push(byte);
+                        self.pc += 1;
+                    }
+                }
+                DirectiveArg::Expr(expr) => {
+                    let value = self.evaluate_expression(expr)?;
+                    let value = check_range(value, BYTE_RANGE, "Byte value")?;
+                    self.binary.push((value & 0xFF) as u8);
+                    self.pc += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Number of fill bytes needed to move the PC forward to a new origin
+    fn org_padding(&self, origin: usize) -> Result<usize, AssemblerError> {
+        if origin < self.pc {
+            return Err(AssemblerError::ValueOutOfRange(format!(
+                "Origin ${:04X} is below the current address ${:04X}", origin, self.pc
+            )));
+        }
+        Ok(origin - self.pc)
+    }
+    
+    /// Build a tokenised BASIC V2 program `<line> SYS <entry>` at the current PC
+    /// 
+    /// The line number defaults to 10 and the entry point to the first
+    /// address after the stub.
+    fn basic_stub(&mut self, directive: &Directive) -> Result<Vec<u8>, AssemblerError> {
+        let args = &directive.args;
+        if args.len() > 2 {
+            return Err(AssemblerError::InvalidExpression(format!(
+                "Too many arguments for .basicstub: {}", args.len()
+            )));
+        }
+        
+        let line = match args.first() {
+            Some(arg) => check_range(self.evaluate_arg(arg)?, 0..=63999, "BASIC line number")?,
+            None => 10,
+        };
+        
+        // The implicit entry point is the first instruction after the stub,
+        // as laid out in the previous pass. Until that is known, or if only
+        // data follows, it is right after the stub, whose length depends on
+        // the number of digits in the SYS address so it is found iteratively
+        let entry = match (args.get(1), self.stub_entry) {
+            (Some(arg), _) => check_range(self.evaluate_arg(arg)?, ADDRESS_RANGE, "BASIC stub entry point")?,
+            (None, Some(entry)) => entry as i64,
+            (None, None) => {
+                let mut entry = self.pc as i64;
+                loop {
+                    let next = self.pc as i64 + basic_stub_bytes(0, 0, entry).len() as i64;
+                    if next == entry {
+                        break entry;
+                    }
+                    entry = next;
+                }
+            }
+        };
+        
+        Ok(basic_stub_bytes(self.pc, line, entry))
+    }
+}
+
+/// Read and parse the lines of a character map file, with their locations
+fn read_charmap_file(path: &Path) -> Result<Vec<(Span, Vec<DirectiveArg>)>, AssemblerError> {
+    let path_name = path.display().to_string();
+    let source = fs::read_to_string(path)
+        .map_err(|e| AssemblerError::CharmapFile(path_name.clone(), e.to_string()))?;
+    
+    let mut lines = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let span = Span::new(&path_name, index + 1, 1);
+        let args = parse_directive_args(line)
+            .map_err(|e| AssemblerError::Parse(e.to_string()).at(&span))?;
+        lines.push((span, args));
+    }
+    Ok(lines)
+}
+
+/// Reject labels and constants that are defined more than once, naming
+/// both definitions
+fn check_duplicate_symbols(ast: &Ast) -> Result<(), AssemblerError> {
+    let mut defined: HashMap<&str, (&str, &Span)> = HashMap::new();
+    
+    for statement in ast.statements() {
+        let (name, kind, span) = match statement {
+            Statement::Label(label) => (label.name.as_str(), "label", &label.span),
+            Statement::Constant(constant) => (constant.name.as_str(), "constant", &constant.span),
+            _ => continue,
+        };
+        
+        if let Some(&(first_kind, first_span)) = defined.get(name) {
+            let message = if kind == first_kind {
+                format!("{} '{}' already defined at {}", kind, name, first_span)
+            } else {
+                format!("{} '{}' already defined as a {} at {}", kind, name, first_kind, first_span)
+            };
+            return Err(AssemblerError::DuplicateLabel(message).at(span));
+        }
+        defined.insert(name, (kind, span));
+    }
+    
+    Ok(())
+}
+
+/// The only argument of a directive that takes a name, given as a string,
+/// an identifier or a number such as `6502`
+fn name_arg(directive: &Directive, what: &str) -> Result<String, AssemblerError> {
+    match directive.args.as_slice() {
+        [DirectiveArg::String(text)] => text.as_plain(),
+        [DirectiveArg::Expr(Expr::Symbol(name))] => Some(name.clone()),
+        [DirectiveArg::Expr(Expr::Number(n))] => Some(n.to_string()),
+        _ => None,
+    }
+    .ok_or_else(|| AssemblerError::InvalidExpression(format!(".{} takes {}", directive.name, what)))
+}
+
+/// Encode a BASIC line `<line> SYS <entry>` located at `addr`, followed by
+/// the end of program marker
+fn basic_stub_bytes(addr: usize, line: i64, entry: i64) -> Vec<u8> {
+    let digits = entry.to_string();
+    let next_line = addr + 2 + 2 + 1 + digits.len() + 1;
+    
+    let mut bytes = vec![
+        (next_line & 0xFF) as u8,
+        ((next_line >> 8) & 0xFF) as u8,
+        (line & 0xFF) as u8,
+        ((line >> 8) & 0xFF) as u8,
+        BASIC_TOKEN_SYS,
+    ];
+    bytes.extend_from_slice(digits.as_bytes());
+    bytes.push(0x00); // End of line
+    bytes.push(0x00); // End of program
+    bytes.push(0x00);
+    bytes
+}
+
+/// Directives that only change the CPU, register widths or how text is
+/// encoded, they are also processed during layout
+const STATE_DIRECTIVES: &[&str] = &[
+    "cpu", "a8", "a16", "i8", "i16", "smart",
+    "encoding", "charmap", "charmapload", "pushcharmap", "popcharmap",
+];
+
+/// 65816 status flag selecting an 8-bit accumulator and memory
+const FLAG_M: i64 = 0x20;
+
+/// 65816 status flag selecting 8-bit index registers
+const FLAG_X: i64 = 0x10;
+
+/// Start of the BASIC program area on the C64
+const BASIC_START: usize = 0x0801;
+
+/// BASIC V2 token for the SYS keyword
+const BASIC_TOKEN_SYS: u8 = 0x9E;
+
+/// Maximum number of layout passes before giving up on convergence
+const MAX_LAYOUT_PASSES: usize = 16;
+
+/// Valid values for byte-sized data, negative values are two's complement
+const BYTE_RANGE: RangeInclusive<i64> = -0x80..=0xFF;
+
+/// Valid values for word-sized data, negative values are two's complement
+const WORD_RANGE: RangeInclusive<i64> = -0x8000..=0xFFFF;
+
+/// Valid zero page addresses
+const ZERO_PAGE_RANGE: RangeInclusive<i64> = 0..=0xFF;
+
+/// Valid 16-bit addresses
+const ADDRESS_RANGE: RangeInclusive<i64> = 0..=0xFFFF;
+
+/// Size of a short branch instruction in bytes
+const SHORT_BRANCH_SIZE: i64 = 2;
+
+/// Offsets of short branches
+const BRANCH_RANGE: RangeInclusive<i64> = -0x80..=0x7F;
+
+/// Offsets of 65816 long branches
+const LONG_BRANCH_RANGE: RangeInclusive<i64> = -0x8000..=0x7FFF;
+
+/// Valid 24-bit addresses of the 65816
+const LONG_ADDRESS_RANGE: RangeInclusive<i64> = 0..=0xFF_FFFF;
+
+/// Check that a value lies within the given range
+fn check_range(value: i64, range: RangeInclusive<i64>, what: &str) -> Result<i64, AssemblerError> {
+    if range.contains(&value) {
+        Ok(value)
+    } else {
+        Err(AssemblerError::ValueOutOfRange(format!(
+            "{} out of range: {}", what, value
+        )))
+    }
 }
 
 /// Assemble the AST into binary
@@ -533,3 +1448,9 @@ pub fn assemble(ast: &Ast) -> Result<Vec<u8>, AssemblerError> {
     let mut assembler = Assembler::new();
     assembler.assemble(ast)
 }
+
+/// Assemble the AST into the contents of an output file of the given format
+pub fn assemble_with_format(ast: &Ast, format: OutputFormat) -> Result<Vec<u8>, AssemblerError> {
+    let mut assembler = Assembler::new();
+    assembler.assemble_with_format(ast, format)
+}
@@ -1,80 +1,179 @@
 // AST representation for C64 assembly language
 
-use std::collections::HashMap;
 use std::fmt;
+use std::str::FromStr;
+use crate::isa::{lookup_mnemonic, InstructionSet};
 
 /// The complete AST representation of an assembly program
 #[derive(Debug, Default, Clone)]
 pub struct Ast {
-    /// List of instructions in the program
-    instructions: Vec<Instruction>,
-    
-    /// Map of labels to their positions
-    labels: HashMap<String, Label>,
-    
-    /// Map of constants to their values
-    constants: HashMap<String, String>,
-    
-    /// List of directives in the program
-    directives: Vec<Directive>,
+    /// Statements of the program in source order
+    statements: Vec<Statement>,
 }
 
 impl Ast {
     pub fn new() -> Self {
         Self {
-            instructions: Vec::new(),
-            labels: HashMap::new(),
-            constants: HashMap::new(),
-            directives: Vec::new(),
+            statements: Vec::new(),
+        }
+    }
+    
+    pub fn add_statement(&mut self, statement: Statement) {
+        self.statements.push(statement);
+    }
+    
+    pub fn insert_statement(&mut self, index: usize, statement: Statement) {
+        self.statements.insert(index, statement);
+    }
+    
+    /// Position after the `.org` directives and constants at the start of
+    /// the program, where generated code such as a BASIC stub is placed at
+    /// the program's origin
+    pub fn start_index(&self) -> usize {
+        let mut start = 0;
+        for (index, statement) in self.statements.iter().enumerate() {
+            match statement {
+                Statement::Directive(directive) if directive.name == "org" => start = index + 1,
+                Statement::Constant(_) => {}
+                _ => break,
+            }
         }
+        start
     }
     
     pub fn add_instruction(&mut self, instruction: Instruction) {
-        self.instructions.push(instruction);
+        self.add_statement(Statement::Instruction(instruction));
     }
     
     pub fn add_label(&mut self, label: Label) {
-        self.labels.insert(label.name.clone(), label);
+        self.add_statement(Statement::Label(label));
     }
     
-    pub fn add_constant(&mut self, name: String, value: String) {
-        self.constants.insert(name, value);
+    pub fn add_constant(&mut self, name: String, value: Expr) {
+        self.add_statement(Statement::Constant(Constant::new(&name, value)));
     }
     
     pub fn add_directive(&mut self, directive: Directive) {
-        self.directives.push(directive);
+        self.add_statement(Statement::Directive(directive));
     }
     
-    pub fn instructions(&self) -> &[Instruction] {
-        &self.instructions
+    pub fn statements(&self) -> &[Statement] {
+        &self.statements
     }
     
-    pub fn labels(&self) -> &HashMap<String, Label> {
-        &self.labels
+    pub fn instructions(&self) -> impl Iterator<Item = &Instruction> {
+        self.statements.iter().filter_map(|s| match s {
+            Statement::Instruction(i) => Some(i),
+            _ => None,
+        })
     }
     
-    pub fn constants(&self) -> &HashMap<String, String> {
-        &self.constants
+    pub fn labels(&self) -> impl Iterator<Item = &Label> {
+        self.statements.iter().filter_map(|s| match s {
+            Statement::Label(l) => Some(l),
+            _ => None,
+        })
+    }
+    
+    pub fn constants(&self) -> impl Iterator<Item = &Constant> {
+        self.statements.iter().filter_map(|s| match s {
+            Statement::Constant(c) => Some(c),
+            _ => None,
+        })
+    }
+    
+    pub fn directives(&self) -> impl Iterator<Item = &Directive> {
+        self.statements.iter().filter_map(|s| match s {
+            Statement::Directive(d) => Some(d),
+            _ => None,
+        })
+    }
+    
+    /// Look up a constant by name
+    pub fn constant(&self, name: &str) -> Option<&Constant> {
+        self.constants().find(|c| c.name == name)
+    }
+}
+
+/// Location of a node in the source code
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// Name of the source file
+    pub file: String,
+    
+    /// Line number (1-based)
+    pub line: usize,
+    
+    /// Column number (1-based)
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(file: &str, line: usize, column: usize) -> Self {
+        Self {
+            file: file.to_string(),
+            line,
+            column,
+        }
     }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// A single statement of the program
+#[derive(Debug, Clone)]
+pub enum Statement {
+    /// Label definition (`name:`)
+    Label(Label),
     
-    pub fn directives(&self) -> &[Directive] {
-        &self.directives
+    /// CPU instruction
+    Instruction(Instruction),
+    
+    /// Assembler directive (`.byte`, `.org`, ...)
+    Directive(Directive),
+    
+    /// Constant assignment (`NAME = value`)
+    Constant(Constant),
+}
+
+impl Statement {
+    /// Source location of the statement
+    pub fn span(&self) -> &Span {
+        match self {
+            Statement::Label(l) => &l.span,
+            Statement::Instruction(i) => &i.span,
+            Statement::Directive(d) => &d.span,
+            Statement::Constant(c) => &c.span,
+        }
     }
 }
 
 /// Represents a 6502 instruction
 #[derive(Debug, Clone)]
 pub struct Instruction {
-    /// The opcode of the instruction
-    pub opcode: Opcode,
+    /// The mnemonic as written, resolved by the instruction set in use
+    /// where the instruction is assembled
+    pub mnemonic: String,
     
     /// The operand of the instruction (if any)
     pub operand: Option<Operand>,
+    
+    /// Location of the instruction in the source
+    pub span: Span,
 }
 
 impl Instruction {
-    pub fn new(opcode: Opcode, operand: Option<Operand>) -> Self {
-        Self { opcode, operand }
+    pub fn new(mnemonic: &str, operand: Option<Operand>) -> Self {
+        Self { mnemonic: mnemonic.to_string(), operand, span: Span::default() }
+    }
+    
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
     }
 }
 
@@ -103,7 +202,7 @@ pub enum Opcode {
     ASL, LSR, ROL, ROR,
     
     // Jumps & Calls
-    JMP, JSR, RTS, RTI,
+    JMP, JSR, RTS, RTI, BRK,
     
     // Branches
     BCC, BCS, BEQ, BMI, BNE, BPL, BVC, BVS,
@@ -114,82 +213,54 @@ pub enum Opcode {
     // No Operation
     NOP,
     
-    // Illegal/Undocumented Opcodes (just a few examples)
+    // Illegal/Undocumented NMOS Opcodes
     SLO, RLA, SRE, RRA, SAX, LAX, DCP, ISC,
+    ANC, ALR, ARR, SBX, USBC, LAS,
+    
+    // Unstable Undocumented Opcodes
+    ANE, LXA, SHA, SHX, SHY, TAS,
+    
+    // Halt the CPU (also known as KIL and HCF)
+    JAM,
     
-    // Halt and Catch Fire
-    HCF,
-}
-
-impl Opcode {
-    pub fn from_str(s: &str) -> Result<Self, String> {
-        match s {
-            "LDA" => Ok(Opcode::LDA),
-            "LDX" => Ok(Opcode::LDX),
-            "LDY" => Ok(Opcode::LDY),
-            "STA" => Ok(Opcode::STA),
-            "STX" => Ok(Opcode::STX),
-            "STY" => Ok(Opcode::STY),
-            "TAX" => Ok(Opcode::TAX),
-            "TAY" => Ok(Opcode::TAY),
-            "TSX" => Ok(Opcode::TSX),
-            "TXA" => Ok(Opcode::TXA),
-            "TXS" => Ok(Opcode::TXS),
-            "TYA" => Ok(Opcode::TYA),
-            "PHA" => Ok(Opcode::PHA),
-            "PHP" => Ok(Opcode::PHP),
-            "PLA" => Ok(Opcode::PLA),
-            "PLP" => Ok(Opcode::PLP),
-            "AND" => Ok(Opcode::AND),
-            "EOR" => Ok(Opcode::EOR),
-            "ORA" => Ok(Opcode::ORA),
-            "BIT" => Ok(Opcode::BIT),
-            "ADC" => Ok(Opcode::ADC),
-            "SBC" => Ok(Opcode::SBC),
-            "CMP" => Ok(Opcode::CMP),
-            "CPX" => Ok(Opcode::CPX),
-            "CPY" => Ok(Opcode::CPY),
-            "INC" => Ok(Opcode::INC),
-            "INX" => Ok(Opcode::INX),
-            "INY" => Ok(Opcode::INY),
-            "DEC" => Ok(Opcode::DEC),
-            "DEX" => Ok(Opcode::DEX),
-            "DEY" => Ok(Opcode::DEY),
-            "ASL" => Ok(Opcode::ASL),
-            "LSR" => Ok(Opcode::LSR),
-            "ROL" => Ok(Opcode::ROL),
-            "ROR" => Ok(Opcode::ROR),
-            "JMP" => Ok(Opcode::JMP),
-            "JSR" => Ok(Opcode::JSR),
-            "RTS" => Ok(Opcode::RTS),
-            "RTI" => Ok(Opcode::RTI),
-            "BCC" => Ok(Opcode::BCC),
-            "BCS" => Ok(Opcode::BCS),
-            "BEQ" => Ok(Opcode::BEQ),
-            "BMI" => Ok(Opcode::BMI),
-            "BNE" => Ok(Opcode::BNE),
-            "BPL" => Ok(Opcode::BPL),
-            "BVC" => Ok(Opcode::BVC),
-            "BVS" => Ok(Opcode::BVS),
-            "CLC" => Ok(Opcode::CLC),
-            "CLD" => Ok(Opcode::CLD),
-            "CLI" => Ok(Opcode::CLI),
-            "CLV" => Ok(Opcode::CLV),
-            "SEC" => Ok(Opcode::SEC),
-            "SED" => Ok(Opcode::SED),
-            "SEI" => Ok(Opcode::SEI),
-            "NOP" => Ok(Opcode::NOP),
-            // Illegal/Undocumented Opcodes
-            "SLO" => Ok(Opcode::SLO),
-            "RLA" => Ok(Opcode::RLA),
-            "SRE" => Ok(Opcode::SRE),
-            "RRA" => Ok(Opcode::RRA),
-            "SAX" => Ok(Opcode::SAX),
-            "LAX" => Ok(Opcode::LAX),
-            "DCP" => Ok(Opcode::DCP),
-            "ISC" => Ok(Opcode::ISC),
-            "HCF" => Ok(Opcode::HCF),
-            _ => Err(format!("Unknown opcode: {}", s)),
+    // 65C02 Additions
+    BRA, STZ, PHX, PHY, PLX, PLY, TRB, TSB,
+    
+    // Rockwell Bit Operations
+    RMB0, RMB1, RMB2, RMB3, RMB4, RMB5, RMB6, RMB7,
+    SMB0, SMB1, SMB2, SMB3, SMB4, SMB5, SMB6, SMB7,
+    BBR0, BBR1, BBR2, BBR3, BBR4, BBR5, BBR6, BBR7,
+    BBS0, BBS1, BBS2, BBS3, BBS4, BBS5, BBS6, BBS7,
+    
+    // WDC Low Power Operations
+    WAI, STP,
+    
+    // 65816 Additions
+    BRL, PER, PEA, PEI, JML, JSL, RTL, MVN, MVP, REP, SEP,
+    PHB, PHD, PHK, PLB, PLD, TCD, TCS, TDC, TSC, TXY, TYX,
+    XBA, XCE, WDM, COP,
+    
+    // Long Branch Pseudo Instructions, a branch or an inverted branch over a JMP
+    JCC, JCS, JEQ, JMI, JNE, JPL, JVC, JVS,
+    
+    // Instructions of other instruction sets, named by their mnemonic
+    Custom(&'static str),
+}
+
+impl FromStr for Opcode {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        lookup_mnemonic(s).ok_or_else(|| format!("Unknown opcode: {}", s))
+    }
+}
+
+// Variants are named after their mnemonics
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Opcode::Custom(name) => write!(f, "{}", name),
+            opcode => write!(f, "{:?}", opcode),
         }
     }
 }
@@ -210,31 +281,105 @@ pub enum AddressingMode {
     IndexedIndirect,    // Indexed indirect (e.g., LDA ($10,X))
     IndirectIndexed,    // Indirect indexed (e.g., LDA ($10),Y)
     Relative,           // Relative addressing for branches (e.g., BNE label)
+    ZeroPageIndirect,   // Zero page indirect, 65C02 (e.g., LDA ($10))
+    AbsoluteIndexedIndirect, // Absolute indexed indirect, 65C02 (e.g., JMP ($1234,X))
+    ZeroPageRelative,   // Zero page and branch target, Rockwell (e.g., BBR0 $10,label)
+    AbsoluteLong,       // 24-bit address, 65816 (e.g., LDA $123456)
+    AbsoluteLongX,      // Indexed 24-bit address with X, 65816 (e.g., LDA $123456,X)
+    ZeroPageIndirectLong, // Direct page indirect long, 65816 (e.g., LDA [$10])
+    ZeroPageIndirectLongIndexed, // Direct page indirect long indexed, 65816 (e.g., LDA [$10],Y)
+    AbsoluteIndirectLong, // Absolute indirect long, 65816 (e.g., JML [$1234])
+    StackRelative,      // Offset from the stack pointer, 65816 (e.g., LDA $03,S)
+    StackRelativeIndirectIndexed, // Stack relative indirect indexed, 65816 (e.g., LDA ($03,S),Y)
+    RelativeLong,       // 16-bit relative offset, 65816 (e.g., BRL label)
+    BlockMove,          // Source and destination banks, 65816 (e.g., MVN $01,$02)
+}
+
+impl AddressingMode {
+    /// The zero page counterpart of an absolute addressing mode
+    pub fn zero_page(self) -> Option<AddressingMode> {
+        match self {
+            AddressingMode::Absolute => Some(AddressingMode::ZeroPage),
+            AddressingMode::AbsoluteX => Some(AddressingMode::ZeroPageX),
+            AddressingMode::AbsoluteY => Some(AddressingMode::ZeroPageY),
+            _ => None,
+        }
+    }
+    
+    /// Whether the operand is a branch offset rather than an address
+    pub fn is_relative(self) -> bool {
+        matches!(self, AddressingMode::Relative | AddressingMode::RelativeLong)
+    }
+    
+    /// The 24-bit counterpart of an absolute addressing mode on the 65816
+    pub fn long(self) -> Option<AddressingMode> {
+        match self {
+            AddressingMode::Absolute => Some(AddressingMode::AbsoluteLong),
+            AddressingMode::AbsoluteX => Some(AddressingMode::AbsoluteLongX),
+            _ => None,
+        }
+    }
+}
+
+/// Address width requested for an operand
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AddressWidth {
+    /// Zero page if the value fits, absolute otherwise
+    #[default]
+    Auto,
+    
+    /// Always zero page (`lda.b`, `lda.z`)
+    ZeroPage,
+    
+    /// Always absolute (`lda.w`, `lda.a`)
+    Absolute,
+    
+    /// Always 24-bit long on the 65816 (`lda.l`)
+    Long,
 }
 
 /// Operand type for instructions
 #[derive(Debug, Clone)]
 pub enum Operand {
     /// Immediate value (#$xx)
-    Immediate(String),
+    Immediate(Expr),
     
     /// Absolute or zero page address ($xxxx or $xx)
-    Address(String),
+    Address(Expr, AddressWidth),
     
     /// Zero page,X or Absolute,X
-    IndexedX(String),
+    IndexedX(Expr, AddressWidth),
     
     /// Zero page,Y or Absolute,Y
-    IndexedY(String),
+    IndexedY(Expr, AddressWidth),
     
     /// Indirect address (($xxxx))
-    Indirect(String),
+    Indirect(Expr),
     
     /// Indexed indirect (($xx,X))
-    IndexedIndirect(String),
+    IndexedIndirect(Expr),
     
     /// Indirect indexed (($xx),Y)
-    IndirectIndexed(String),
+    IndirectIndexed(Expr),
+    
+    /// Accumulator (A), as in `asl a`
+    Accumulator,
+    
+    /// Two values, a zero page address and branch target as in
+    /// `bbr0 $10,loop` or source and destination banks as in `mvn $01,$02`
+    Pair(Expr, Expr),
+    
+    /// Stack relative ($xx,S)
+    StackRelative(Expr),
+    
+    /// Stack relative indirect indexed (($xx,S),Y)
+    StackRelativeIndirectIndexed(Expr),
+    
+    /// Indirect long ([$xx] or [$xxxx])
+    IndirectLong(Expr),
+    
+    /// Indirect long indexed ([$xx],Y)
+    IndirectLongIndexed(Expr),
 }
 
 // Implement Display for the Operand enum so it can be converted to string
@@ -242,77 +387,103 @@ impl fmt::Display for Operand {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Operand::Immediate(val) => write!(f, "#{}", val),
-            Operand::Address(addr) => write!(f, "{}", addr),
-            Operand::IndexedX(addr) => write!(f, "{},X", addr),
-            Operand::IndexedY(addr) => write!(f, "{},Y", addr),
+            Operand::Address(addr, _) => write!(f, "{}", addr),
+            Operand::IndexedX(addr, _) => write!(f, "{},X", addr),
+            Operand::IndexedY(addr, _) => write!(f, "{},Y", addr),
             Operand::Indirect(addr) => write!(f, "({})", addr),
             Operand::IndexedIndirect(addr) => write!(f, "({},X)", addr),
-            Operand::IndirectIndexed(addr) => write!(f, "({},Y)", addr),
+            Operand::IndirectIndexed(addr) => write!(f, "({}),Y", addr),
+            Operand::Accumulator => write!(f, "A"),
+            Operand::Pair(first, second) => write!(f, "{},{}", first, second),
+            Operand::StackRelative(offset) => write!(f, "{},S", offset),
+            Operand::StackRelativeIndirectIndexed(offset) => write!(f, "({},S),Y", offset),
+            Operand::IndirectLong(addr) => write!(f, "[{}]", addr),
+            Operand::IndirectLongIndexed(addr) => write!(f, "[{}],Y", addr),
         }
     }
 }
 
 impl Operand {
-    pub fn parse(s: &str) -> Self {
-        if s.starts_with('#') {
-            Operand::Immediate(s[1..].to_string())
-        } else if s.ends_with(",x") || s.ends_with(",X") {
-            let addr = &s[..s.len() - 2];
-            Operand::IndexedX(addr.to_string())
-        } else if s.ends_with(",y") || s.ends_with(",Y") {
-            let addr = &s[..s.len() - 2];
-            Operand::IndexedY(addr.to_string())
-        } else if s.starts_with('(') && s.ends_with("),y") || s.ends_with("),Y") {
-            let addr = &s[1..s.len() - 3];
-            Operand::IndirectIndexed(addr.to_string())
-        } else if s.starts_with('(') && (s.ends_with(",x)") || s.ends_with(",X)")) {
-            let addr = &s[1..s.len() - 3];
-            Operand::IndexedIndirect(addr.to_string())
-        } else if s.starts_with('(') && s.ends_with(')') {
-            let addr = &s[1..s.len() - 1];
-            Operand::Indirect(addr.to_string())
-        } else {
-            Operand::Address(s.to_string())
+    /// The expression giving the operand's value or address
+    pub fn value(&self) -> Option<&Expr> {
+        match self {
+            Operand::Immediate(expr) |
+            Operand::Address(expr, _) |
+            Operand::IndexedX(expr, _) |
+            Operand::IndexedY(expr, _) |
+            Operand::Indirect(expr) |
+            Operand::IndexedIndirect(expr) |
+            Operand::IndirectIndexed(expr) |
+            Operand::Pair(expr, _) |
+            Operand::StackRelative(expr) |
+            Operand::StackRelativeIndirectIndexed(expr) |
+            Operand::IndirectLong(expr) |
+            Operand::IndirectLongIndexed(expr) => Some(expr),
+            Operand::Accumulator => None,
+        }
+    }
+    
+    /// The second value of a two value operand
+    pub fn second(&self) -> Option<&Expr> {
+        match self {
+            Operand::Pair(_, second) => Some(second),
+            _ => None,
+        }
+    }
+    
+    /// The address width requested for the operand
+    pub fn width(&self) -> AddressWidth {
+        match self {
+            Operand::Address(_, width) |
+            Operand::IndexedX(_, width) |
+            Operand::IndexedY(_, width) => *width,
+            _ => AddressWidth::Auto,
+        }
+    }
+    
+    /// The operand with an address width, unless it already has one
+    pub fn or_width(self, width: AddressWidth) -> Self {
+        match self {
+            Operand::Address(expr, AddressWidth::Auto) => Operand::Address(expr, width),
+            Operand::IndexedX(expr, AddressWidth::Auto) => Operand::IndexedX(expr, width),
+            Operand::IndexedY(expr, AddressWidth::Auto) => Operand::IndexedY(expr, width),
+            operand => operand,
         }
     }
     
-    pub fn get_addressing_mode(&self, opcode: Opcode) -> AddressingMode {
-        // Branch instructions always use relative addressing
-        if matches!(opcode, 
-            Opcode::BCC | Opcode::BCS | Opcode::BEQ | 
-            Opcode::BMI | Opcode::BNE | Opcode::BPL | 
-            Opcode::BVC | Opcode::BVS) {
-            return AddressingMode::Relative;
+    /// Addressing mode of the operand for an instruction of an instruction set
+    pub fn get_addressing_mode(&self, opcode: Opcode, isa: &dyn InstructionSet) -> AddressingMode {
+        // The first mode if the instruction has it, otherwise the second
+        let prefer = |mode, fallback| if isa.has_mode(opcode, mode) { mode } else { fallback };
+        
+        // Branch targets are encoded as offsets
+        if let (Operand::Address(_, _), Some(mode)) = (self, isa.branch_mode(opcode)) {
+            return mode;
         }
-            
+        
+        // Addresses default to absolute unless zero page is forced, the
+        // assembler narrows them to zero page once the value is known
         match self {
             Operand::Immediate(_) => AddressingMode::Immediate,
-            Operand::Address(addr) => {
-                // Try to determine if it's zero page or absolute
-                // This is a simplification; you'd need to evaluate expressions
-                if addr.starts_with('$') && addr.len() <= 3 {
-                    AddressingMode::ZeroPage
-                } else {
-                    AddressingMode::Absolute
-                }
-            }
-            Operand::IndexedX(addr) => {
-                if addr.starts_with('$') && addr.len() <= 3 {
-                    AddressingMode::ZeroPageX
-                } else {
-                    AddressingMode::AbsoluteX
-                }
-            }
-            Operand::IndexedY(addr) => {
-                if addr.starts_with('$') && addr.len() <= 3 {
-                    AddressingMode::ZeroPageY
-                } else {
-                    AddressingMode::AbsoluteY
-                }
-            }
-            Operand::Indirect(_) => AddressingMode::Indirect,
-            Operand::IndexedIndirect(_) => AddressingMode::IndexedIndirect,
+            Operand::Address(_, AddressWidth::ZeroPage) => AddressingMode::ZeroPage,
+            Operand::Address(_, AddressWidth::Long) => AddressingMode::AbsoluteLong,
+            Operand::Address(_, _) => AddressingMode::Absolute,
+            Operand::IndexedX(_, AddressWidth::ZeroPage) => AddressingMode::ZeroPageX,
+            Operand::IndexedX(_, AddressWidth::Long) => AddressingMode::AbsoluteLongX,
+            Operand::IndexedX(_, _) => AddressingMode::AbsoluteX,
+            Operand::IndexedY(_, AddressWidth::ZeroPage) => AddressingMode::ZeroPageY,
+            Operand::IndexedY(_, _) => AddressingMode::AbsoluteY,
+            // Jumps take a full address in parentheses, other instructions
+            // use the zero page forms
+            Operand::Indirect(_) => prefer(AddressingMode::Indirect, AddressingMode::ZeroPageIndirect),
+            Operand::IndexedIndirect(_) => prefer(AddressingMode::AbsoluteIndexedIndirect, AddressingMode::IndexedIndirect),
             Operand::IndirectIndexed(_) => AddressingMode::IndirectIndexed,
+            Operand::IndirectLong(_) => prefer(AddressingMode::AbsoluteIndirectLong, AddressingMode::ZeroPageIndirectLong),
+            Operand::IndirectLongIndexed(_) => AddressingMode::ZeroPageIndirectLongIndexed,
+            Operand::StackRelative(_) => AddressingMode::StackRelative,
+            Operand::StackRelativeIndirectIndexed(_) => AddressingMode::StackRelativeIndirectIndexed,
+            Operand::Accumulator => AddressingMode::Accumulator,
+            Operand::Pair(_, _) => prefer(AddressingMode::BlockMove, AddressingMode::ZeroPageRelative),
         }
     }
 }
@@ -325,6 +496,9 @@ pub struct Label {
     
     /// Position of the label (to be filled during assembly)
     pub position: Option<usize>,
+    
+    /// Location of the label definition in the source
+    pub span: Span,
 }
 
 impl Label {
@@ -332,15 +506,33 @@ impl Label {
         Self {
             name: name.to_string(),
             position: None,
+            span: Span::default(),
         }
     }
     
+    /// Whether this is an anonymous `-`, `+` or `:` label
+    pub fn is_anonymous(&self) -> bool {
+        is_anonymous_label(&self.name)
+    }
+    
     pub fn with_position(name: &str, position: usize) -> Self {
         Self {
             name: name.to_string(),
             position: Some(position),
+            span: Span::default(),
         }
     }
+    
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+/// Whether a symbol name refers to an anonymous label, these are numbered
+/// per direction as `-0`, `-1`, ... and `+0`, `+1`, ...
+pub fn is_anonymous_label(name: &str) -> bool {
+    name.starts_with(['-', '+'])
 }
 
 /// Represents an assembly directive (like .byte, .word, etc.)
@@ -349,15 +541,230 @@ pub struct Directive {
     /// Name of the directive
     pub name: String,
     
-    /// Value of the directive
-    pub value: String,
+    /// Comma separated arguments of the directive
+    pub args: Vec<DirectiveArg>,
+    
+    /// Location of the directive in the source
+    pub span: Span,
 }
 
 impl Directive {
-    pub fn new(name: &str, value: &str) -> Self {
+    pub fn new(name: &str, args: Vec<DirectiveArg>) -> Self {
         Self {
             name: name.to_string(),
-            value: value.to_string(),
+            args,
+            span: Span::default(),
+        }
+    }
+    
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+/// A single argument of a directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirectiveArg {
+    /// String literal, with escape sequences resolved
+    String(Text),
+    
+    /// Expression
+    Expr(Expr),
+}
+
+impl fmt::Display for DirectiveArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DirectiveArg::String(text) => write!(f, "\"{}\"", text),
+            DirectiveArg::Expr(expr) => write!(f, "{}", expr),
+        }
+    }
+}
+
+/// Contents of a string literal
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Text {
+    pub chars: Vec<TextChar>,
+}
+
+/// A character of a string literal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextChar {
+    /// Character, its byte depends on the active text encoding
+    Char(char),
+    
+    /// Named PETSCII control code (`{clr}`, `{white}`)
+    Control(u8),
+    
+    /// Numeric byte (`{$93}`), emitted unchanged
+    Byte(u8),
+}
+
+impl Text {
+    /// Number of bytes the text encodes to
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+    
+    /// The text as a plain string, `None` if it contains control codes
+    pub fn as_plain(&self) -> Option<String> {
+        self.chars
+            .iter()
+            .map(|c| match c {
+                TextChar::Char(c) => Some(*c),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl From<&str> for Text {
+    fn from(s: &str) -> Self {
+        Self { chars: s.chars().map(TextChar::Char).collect() }
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in &self.chars {
+            match c {
+                TextChar::Char(c @ ('"' | '\\' | '{')) => write!(f, "\\{}", c)?,
+                TextChar::Char('\n') => write!(f, "\\n")?,
+                TextChar::Char('\r') => write!(f, "\\r")?,
+                TextChar::Char('\t') => write!(f, "\\t")?,
+                TextChar::Char(c) => write!(f, "{}", c)?,
+                TextChar::Control(code) | TextChar::Byte(code) => write!(f, "{{${:02X}}}", code)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Represents a constant assignment (`NAME = value`)
+#[derive(Debug, Clone)]
+pub struct Constant {
+    /// Name of the constant
+    pub name: String,
+    
+    /// Value of the constant
+    pub value: Expr,
+    
+    /// Location of the constant definition in the source
+    pub span: Span,
+}
+
+impl Constant {
+    pub fn new(name: &str, value: Expr) -> Self {
+        Self {
+            name: name.to_string(),
+            value,
+            span: Span::default(),
+        }
+    }
+    
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+/// Expression tree for operands, constants and directive values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// Numeric literal
+    Number(i64),
+    
+    /// Character literal, its value depends on the active text encoding
+    Char(char),
+    
+    /// Reference to a label or constant
+    Symbol(String),
+    
+    /// Address of the current statement (`*`)
+    CurrentPc,
+    
+    /// Unary operation
+    Unary(UnaryOp, Box<Expr>),
+    
+    /// Binary operation
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+/// Unary operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,             // -x
+    BitNot,             // ~x
+    LogicalNot,         // !x
+    LowByte,            // <x
+    HighByte,           // >x
+    BankByte,           // ^x
+}
+
+/// Binary operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add, Sub, Mul, Div, Mod,
+    BitAnd, BitOr, BitXor, Shl, Shr,
+    Eq, Ne, Lt, Le, Gt, Ge,
+    LogicalAnd, LogicalOr,
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            UnaryOp::Negate => "-",
+            UnaryOp::BitNot => "~",
+            UnaryOp::LogicalNot => "!",
+            UnaryOp::LowByte => "<",
+            UnaryOp::HighByte => ">",
+            UnaryOp::BankByte => "^",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::LogicalAnd => "&&",
+            BinaryOp::LogicalOr => "||",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(n) if *n < 0 => write!(f, "-${:X}", n.unsigned_abs()),
+            Expr::Number(n) => write!(f, "${:X}", n),
+            Expr::Char(c) => write!(f, "{:?}", c),
+            Expr::Symbol(name) => write!(f, "{}", name),
+            Expr::CurrentPc => write!(f, "*"),
+            Expr::Unary(op, expr) => write!(f, "{}{}", op, expr),
+            Expr::Binary(op, lhs, rhs) => write!(f, "({} {} {})", lhs, op, rhs),
         }
     }
 }
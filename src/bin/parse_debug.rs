@@ -10,10 +10,10 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     match result {
         Ok(ast) => {
             println!("Successfully parsed AST:");
-            println!("Instructions: {}", ast.instructions().len());
-            println!("Labels: {}", ast.labels().len());
-            println!("Directives: {}", ast.directives().len());
-            println!("Constants: {}", ast.constants().len());
+            println!("Instructions: {}", ast.instructions().count());
+            println!("Labels: {}", ast.labels().count());
+            println!("Directives: {}", ast.directives().count());
+            println!("Constants: {}", ast.constants().count());
         },
         Err(e) => {
             println!("Parse error: {:?}", e);
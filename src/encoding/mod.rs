@@ -0,0 +1,187 @@
+// Text encodings for C64 string and character data
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Encoding used to turn characters into bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// Plain 7-bit ASCII
+    #[default]
+    Ascii,
+    
+    /// PETSCII for the uppercase/graphics character set, letters of either
+    /// case print as capitals
+    Petscii,
+    
+    /// PETSCII for the lowercase/uppercase character set
+    PetsciiLower,
+    
+    /// Screen codes for the uppercase/graphics character set, letters of
+    /// either case show as capitals
+    Screen,
+    
+    /// Screen codes for the lowercase/uppercase character set
+    ScreenLower,
+}
+
+impl Encoding {
+    /// Whether PETSCII control codes can be used in this encoding
+    pub fn has_control_codes(self) -> bool {
+        matches!(self, Encoding::Petscii | Encoding::PetsciiLower)
+    }
+    
+    /// Byte for a character, or `None` if the encoding has no mapping for it
+    pub fn encode_char(self, c: char) -> Option<u8> {
+        match self {
+            Encoding::Ascii => c.is_ascii().then_some(c as u8),
+            Encoding::Petscii => match c {
+                'a'..='z' => Some(c as u8 - b'a' + 0x41),
+                '\n' | '\r' => Some(0x0D),
+                'π' => Some(0xFF),
+                _ => petscii_common(c),
+            },
+            Encoding::PetsciiLower => match c {
+                'a'..='z' => Some(c as u8 - b'a' + 0x41),
+                'A'..='Z' => Some(c as u8 - b'A' + 0xC1),
+                '\n' | '\r' => Some(0x0D),
+                _ => petscii_common(c),
+            },
+            Encoding::Screen => match c {
+                'a'..='z' => Some(c as u8 - b'a' + 0x01),
+                'π' => Some(0x5E),
+                _ => petscii_common(c).and_then(petscii_to_screen),
+            },
+            Encoding::ScreenLower => match c {
+                'a'..='z' => Some(c as u8 - b'a' + 0x01),
+                'A'..='Z' => Some(c as u8 - b'A' + 0x41),
+                _ => petscii_common(c).and_then(petscii_to_screen),
+            },
+        }
+    }
+}
+
+/// PETSCII control code for a brace escape name such as `clr` or `rvs on`,
+/// using the names of VICE's petcat
+pub fn control_code(name: &str) -> Option<u8> {
+    let name = name.trim().to_lowercase();
+    CONTROL_CODES
+        .iter()
+        .find(|(names, _)| names.contains(&name.as_str()))
+        .map(|&(_, code)| code)
+}
+
+/// Brace escape names and the PETSCII control codes they stand for
+const CONTROL_CODES: &[(&[&str], u8)] = &[
+    (&["stop"], 0x03),
+    (&["wht", "white"], 0x05),
+    (&["dish"], 0x08),
+    (&["ensh"], 0x09),
+    (&["return", "cr"], 0x0D),
+    (&["swlc", "lower"], 0x0E),
+    (&["down"], 0x11),
+    (&["rvon", "rvs on"], 0x12),
+    (&["home"], 0x13),
+    (&["del"], 0x14),
+    (&["red"], 0x1C),
+    (&["rght", "right"], 0x1D),
+    (&["grn", "green"], 0x1E),
+    (&["blu", "blue"], 0x1F),
+    (&["orng", "orange"], 0x81),
+    (&["f1"], 0x85),
+    (&["f3"], 0x86),
+    (&["f5"], 0x87),
+    (&["f7"], 0x88),
+    (&["f2"], 0x89),
+    (&["f4"], 0x8A),
+    (&["f6"], 0x8B),
+    (&["f8"], 0x8C),
+    (&["sret"], 0x8D),
+    (&["swuc", "upper"], 0x8E),
+    (&["blk", "black"], 0x90),
+    (&["up"], 0x91),
+    (&["rvof", "rvs off"], 0x92),
+    (&["clr", "clear"], 0x93),
+    (&["inst", "insert"], 0x94),
+    (&["brn", "brown"], 0x95),
+    (&["lred", "light red", "pink"], 0x96),
+    (&["gry1", "dark gray", "dark grey"], 0x97),
+    (&["gry2", "gray", "grey"], 0x98),
+    (&["lgrn", "light green"], 0x99),
+    (&["lblu", "light blue"], 0x9A),
+    (&["gry3", "light gray", "light grey"], 0x9B),
+    (&["pur", "purple"], 0x9C),
+    (&["left"], 0x9D),
+    (&["yel", "yellow"], 0x9E),
+    (&["cyn", "cyan"], 0x9F),
+];
+
+/// Printable characters shared by both PETSCII character sets, with
+/// uppercase letters in the $41-$5A range
+fn petscii_common(c: char) -> Option<u8> {
+    match c {
+        ' '..='@' | 'A'..='Z' | '[' | ']' => Some(c as u8),
+        '£' => Some(0x5C),
+        '↑' => Some(0x5E),
+        '←' => Some(0x5F),
+        _ => None,
+    }
+}
+
+/// Screen code of a printable PETSCII character in the $20-$5F range
+fn petscii_to_screen(byte: u8) -> Option<u8> {
+    match byte {
+        0x20..=0x3F => Some(byte),
+        0x40..=0x5F => Some(byte - 0x40),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Encoding::Ascii => "ascii",
+            Encoding::Petscii => "petscii",
+            Encoding::PetsciiLower => "petscii_lower",
+            Encoding::Screen => "screen",
+            Encoding::ScreenLower => "screen_lower",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ascii" => Ok(Encoding::Ascii),
+            "petscii" | "petscii_upper" => Ok(Encoding::Petscii),
+            "petscii_lower" => Ok(Encoding::PetsciiLower),
+            "screen" | "scr" | "screen_upper" => Ok(Encoding::Screen),
+            "screen_lower" | "scr_lower" => Ok(Encoding::ScreenLower),
+            _ => Err(format!(
+                "Unknown encoding: {} (expected ascii, petscii, petscii_lower, screen or screen_lower)", s
+            )),
+        }
+    }
+}
+
+/// User defined character mapping, takes precedence over the encoding
+#[derive(Debug, Clone, Default)]
+pub struct Charmap {
+    map: HashMap<char, u8>,
+}
+
+impl Charmap {
+    /// Map a character to a byte, replacing any previous mapping
+    pub fn insert(&mut self, c: char, byte: u8) {
+        self.map.insert(c, byte);
+    }
+    
+    /// Byte for a character, if it has been mapped
+    pub fn get(&self, c: char) -> Option<u8> {
+        self.map.get(&c).copied()
+    }
+}
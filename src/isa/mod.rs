@@ -0,0 +1,331 @@
+// Instruction sets of the 6502 CPU family for C64 assembly
+
+mod tables;
+
+use crate::ast::{AddressingMode, Opcode};
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use self::tables::{build_decode_only_entries, build_opcode_table};
+
+/// Opcode lookup table keyed by instruction and addressing mode
+pub type OpcodeTable = HashMap<(Opcode, AddressingMode), OpcodeEntry>;
+
+/// Everything the assembler and disassembler know about a processor's
+/// instructions, so new processors only need a new implementation
+pub trait InstructionSet: Send + Sync {
+    /// Name of the processor, for messages
+    fn name(&self) -> &str;
+    
+    /// Table entry of an instruction in an addressing mode
+    fn entry(&self, opcode: Opcode, mode: AddressingMode) -> Option<OpcodeEntry>;
+    
+    /// All instructions of the processor
+    fn instructions(&self) -> Vec<Opcode>;
+    
+    /// Addressing modes of an instruction, empty if the processor lacks it
+    fn addressing_modes(&self, opcode: Opcode) -> Vec<AddressingMode>;
+    
+    /// Instruction, addressing mode and table entry of an opcode byte
+    fn decode(&self, byte: u8) -> Option<(Opcode, AddressingMode, OpcodeEntry)>;
+    
+    /// Instruction for a mnemonic, by default the names of the instructions
+    fn mnemonic(&self, name: &str) -> Option<Opcode> {
+        self.instructions().into_iter().find(|opcode| opcode.to_string().eq_ignore_ascii_case(name))
+    }
+    
+    /// Whether a mnemonic only stands for the absolute forms of its
+    /// instruction, so its operand is never narrowed to zero page
+    fn absolute_alias(&self, _name: &str) -> bool {
+        false
+    }
+    
+    /// Whether the processor has an instruction in any addressing mode
+    fn supports(&self, opcode: Opcode) -> bool {
+        !self.addressing_modes(opcode).is_empty()
+    }
+    
+    /// Whether an instruction can be used in an addressing mode
+    fn has_mode(&self, opcode: Opcode, mode: AddressingMode) -> bool {
+        self.entry(opcode, mode).is_some()
+    }
+    
+    /// Relative addressing mode of a branch instruction, whose address
+    /// operand is encoded as an offset
+    fn branch_mode(&self, opcode: Opcode) -> Option<AddressingMode> {
+        self.addressing_modes(opcode).into_iter().find(|mode| mode.is_relative())
+    }
+    
+    /// Whether programs can be placed above the first 64K, which takes a
+    /// long jump to reach
+    fn long_addressing(&self) -> bool {
+        self.has_mode(Opcode::JML, AddressingMode::AbsoluteLong)
+    }
+}
+
+/// Instruction set described by an opcode table
+pub struct TableInstructionSet {
+    /// Name of the processor
+    name: String,
+    
+    /// Entries by instruction and addressing mode, for encoding
+    table: OpcodeTable,
+    
+    /// Instruction, addressing mode and entry by opcode byte, for decoding
+    decoder: HashMap<u8, (Opcode, AddressingMode, OpcodeEntry)>,
+    
+    /// Instruction by uppercase mnemonic, including aliases and long branches
+    mnemonics: HashMap<String, Opcode>,
+}
+
+impl TableInstructionSet {
+    pub fn new(name: &str, table: OpcodeTable) -> Self {
+        let mut decoder: HashMap<u8, (Opcode, AddressingMode, OpcodeEntry)> = HashMap::new();
+        for (&(opcode, mode), &entry) in &table {
+            // A byte shared by several entries decodes to the preferred one,
+            // such as JML rather than the long form of JMP
+            decoder
+                .entry(entry.byte)
+                .and_modify(|decoded| if entry.preferred { *decoded = (opcode, mode, entry) })
+                .or_insert((opcode, mode, entry));
+        }
+        
+        let supported: HashSet<Opcode> = table.keys().map(|&(opcode, _)| opcode).collect();
+        let mut mnemonics: HashMap<String, Opcode> = supported
+            .iter()
+            .map(|&opcode| (opcode.to_string().to_uppercase(), opcode))
+            .collect();
+        for &(pseudo, branch) in LONG_BRANCHES {
+            if supported.contains(&branch) {
+                mnemonics.insert(pseudo.to_string(), pseudo);
+            }
+        }
+        for &(alias, opcode) in ALIASES {
+            if supported.contains(&opcode) {
+                mnemonics.insert(alias.to_string(), opcode);
+            }
+        }
+        
+        Self { name: name.to_string(), table, decoder, mnemonics }
+    }
+    
+    /// Add bytes that decode to an instruction of the table but are never
+    /// emitted, such as the undocumented duplicates of NOP
+    pub fn decode_only(mut self, entries: impl IntoIterator<Item = (Opcode, AddressingMode, OpcodeEntry)>) -> Self {
+        for (opcode, mode, entry) in entries {
+            self.decoder.entry(entry.byte).or_insert((opcode, mode, entry));
+        }
+        self
+    }
+}
+
+impl InstructionSet for TableInstructionSet {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn entry(&self, opcode: Opcode, mode: AddressingMode) -> Option<OpcodeEntry> {
+        self.table.get(&(opcode, mode)).copied()
+    }
+    
+    fn instructions(&self) -> Vec<Opcode> {
+        let mut seen = HashSet::new();
+        self.table.keys().map(|&(opcode, _)| opcode).filter(|&opcode| seen.insert(opcode)).collect()
+    }
+    
+    fn addressing_modes(&self, opcode: Opcode) -> Vec<AddressingMode> {
+        self.table.keys().filter(|&&(op, _)| op == opcode).map(|&(_, mode)| mode).collect()
+    }
+    
+    fn decode(&self, byte: u8) -> Option<(Opcode, AddressingMode, OpcodeEntry)> {
+        self.decoder.get(&byte).copied()
+    }
+    
+    fn mnemonic(&self, name: &str) -> Option<Opcode> {
+        self.mnemonics.get(&name.to_uppercase()).copied()
+    }
+    
+    fn absolute_alias(&self, name: &str) -> bool {
+        ABSOLUTE_ALIASES.iter().any(|alias| alias.eq_ignore_ascii_case(name)) && self.mnemonic(name).is_some()
+    }
+}
+
+/// Instruction for a mnemonic or alias of any built-in processor
+pub fn lookup_mnemonic(name: &str) -> Option<Opcode> {
+    Cpu::ALL.iter().find_map(|cpu| cpu.instruction_set().mnemonic(name))
+}
+
+/// Branch a long branch pseudo instruction such as `jeq` stands for
+pub fn short_branch(opcode: Opcode) -> Option<Opcode> {
+    LONG_BRANCHES.iter().find(|&&(pseudo, _)| pseudo == opcode).map(|&(_, branch)| branch)
+}
+
+/// Conditional branch taken exactly when the given one is not
+pub fn inverse_branch(opcode: Opcode) -> Option<Opcode> {
+    INVERSE_BRANCHES.iter().find(|&&(branch, _)| branch == opcode).map(|&(_, inverse)| inverse)
+}
+
+/// Long branch pseudo instructions and the branches they stand for
+const LONG_BRANCHES: &[(Opcode, Opcode)] = &[
+    (Opcode::JCC, Opcode::BCC), (Opcode::JCS, Opcode::BCS), (Opcode::JEQ, Opcode::BEQ), (Opcode::JMI, Opcode::BMI),
+    (Opcode::JNE, Opcode::BNE), (Opcode::JPL, Opcode::BPL), (Opcode::JVC, Opcode::BVC), (Opcode::JVS, Opcode::BVS),
+];
+
+/// Conditional branches and the branches of the opposite condition
+const INVERSE_BRANCHES: &[(Opcode, Opcode)] = &[
+    (Opcode::BCC, Opcode::BCS), (Opcode::BCS, Opcode::BCC), (Opcode::BEQ, Opcode::BNE), (Opcode::BNE, Opcode::BEQ),
+    (Opcode::BMI, Opcode::BPL), (Opcode::BPL, Opcode::BMI), (Opcode::BVC, Opcode::BVS), (Opcode::BVS, Opcode::BVC),
+];
+
+/// Alternative mnemonics used by other assemblers and opcode lists
+const ALIASES: &[(&str, Opcode)] = &[
+    // Multi-byte NOPs
+    ("DOP", Opcode::NOP), ("TOP", Opcode::NOP), ("SKB", Opcode::NOP), ("SKW", Opcode::NOP),
+    // Undocumented NMOS opcodes
+    ("ASO", Opcode::SLO), ("LSE", Opcode::SRE), ("AAX", Opcode::SAX), ("DCM", Opcode::DCP),
+    ("ISB", Opcode::ISC), ("INS", Opcode::ISC), ("ASR", Opcode::ALR), ("AXS", Opcode::SBX),
+    ("LAR", Opcode::LAS), ("XAA", Opcode::ANE), ("ATX", Opcode::LXA), ("AHX", Opcode::SHA),
+    ("AXA", Opcode::SHA), ("SXA", Opcode::SHX), ("SYA", Opcode::SHY), ("SHS", Opcode::TAS),
+    ("KIL", Opcode::JAM), ("HLT", Opcode::JAM), ("HCF", Opcode::JAM),
+    // 65816 register transfers
+    ("TAD", Opcode::TCD), ("TDA", Opcode::TDC), ("TSA", Opcode::TSC), ("SWA", Opcode::XBA),
+];
+
+/// Aliases that only stand for the absolute forms of their instruction
+const ABSOLUTE_ALIASES: &[&str] = &["TOP", "SKW"];
+
+/// Processor whose instruction set is assembled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Cpu {
+    /// NMOS 6502, including the undocumented opcodes
+    Mos6502,
+    
+    /// NMOS 6510 of the C64, same instruction set as the 6502
+    #[default]
+    Mos6510,
+    
+    /// CMOS 65C02 without the Rockwell bit instructions
+    Cmos65C02,
+    
+    /// Rockwell R65C02 with RMB/SMB/BBR/BBS
+    R65C02,
+    
+    /// WDC W65C02S, the Rockwell set plus WAI and STP
+    W65C02,
+    
+    /// WDC 65C816 of the SuperCPU and SNES, without the Rockwell instructions
+    W65816,
+}
+
+impl Cpu {
+    /// All supported processors
+    pub const ALL: [Cpu; 6] = [Cpu::Mos6502, Cpu::Mos6510, Cpu::Cmos65C02, Cpu::R65C02, Cpu::W65C02, Cpu::W65816];
+    
+    /// Instruction set of the processor, built on first use
+    pub fn instruction_set(self) -> Arc<dyn InstructionSet> {
+        static SETS: Lazy<Vec<Arc<dyn InstructionSet>>> = Lazy::new(|| {
+            Cpu::ALL
+                .iter()
+                .map(|&cpu| {
+                    let isa = TableInstructionSet::new(&cpu.to_string(), build_opcode_table(cpu))
+                        .decode_only(build_decode_only_entries(cpu));
+                    Arc::new(isa) as Arc<dyn InstructionSet>
+                })
+                .collect()
+        });
+        
+        let index = Cpu::ALL.iter().position(|&cpu| cpu == self).unwrap_or_default();
+        Arc::clone(&SETS[index])
+    }
+}
+
+impl fmt::Display for Cpu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Cpu::Mos6502 => "6502",
+            Cpu::Mos6510 => "6510",
+            Cpu::Cmos65C02 => "65c02",
+            Cpu::R65C02 => "r65c02",
+            Cpu::W65C02 => "w65c02",
+            Cpu::W65816 => "65816",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Cpu {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "6502" => Ok(Cpu::Mos6502),
+            "6510" => Ok(Cpu::Mos6510),
+            "65c02" => Ok(Cpu::Cmos65C02),
+            "r65c02" => Ok(Cpu::R65C02),
+            "w65c02" | "w65c02s" => Ok(Cpu::W65C02),
+            "65816" | "65c816" | "w65c816" | "w65c816s" => Ok(Cpu::W65816),
+            _ => Err(format!(
+                "Unknown CPU: {} (expected 6502, 6510, 65c02, r65c02, w65c02 or 65816)", s
+            )),
+        }
+    }
+}
+
+/// Represents an opcode lookup entry
+#[derive(Debug, Clone, Copy)]
+pub struct OpcodeEntry {
+    /// The opcode byte
+    pub byte: u8,
+    
+    /// Number of bytes including the opcode byte itself
+    pub size: u8,
+    
+    /// Number of cycles required to execute this instruction
+    pub cycles: u8,
+    
+    /// Whether the result of the instruction is unreliable on real hardware
+    pub unstable: bool,
+    
+    /// Register whose width sets the size of an immediate operand (65816)
+    pub sized_by: Option<Register>,
+    
+    /// Whether the opcode byte decodes to this entry when other entries
+    /// share it
+    pub preferred: bool,
+}
+
+/// Register of the 65816 that can be switched between 8 and 16 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    /// Accumulator and memory, the M flag
+    Accumulator,
+    
+    /// X and Y index registers, the X flag
+    Index,
+}
+
+impl OpcodeEntry {
+    pub fn new(byte: u8, size: u8, cycles: u8) -> Self {
+        Self { byte, size, cycles, unstable: false, sized_by: None, preferred: false }
+    }
+    
+    /// Make the immediate operand as wide as a 65816 register
+    pub fn sized_by(mut self, register: Register) -> Self {
+        self.sized_by = Some(register);
+        self
+    }
+    
+    /// Mark the entry as an unstable undocumented opcode
+    pub fn unstable(mut self) -> Self {
+        self.unstable = true;
+        self
+    }
+    
+    /// Decode the opcode byte to this entry rather than others sharing it
+    pub fn preferred(mut self) -> Self {
+        self.preferred = true;
+        self
+    }
+}
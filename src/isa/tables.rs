@@ -0,0 +1,549 @@
+// Opcode tables for the 6502 CPU family
+// This file contains the complete opcode mapping for each supported processor
+
+use crate::ast::{Opcode, AddressingMode};
+use super::{Cpu, OpcodeEntry, OpcodeTable, Register};
+use std::collections::HashMap;
+
+/// Build a complete opcode lookup table for the instructions of a processor
+pub fn build_opcode_table(cpu: Cpu) -> OpcodeTable {
+    let mut table = HashMap::new();
+    add_documented_opcodes(&mut table);
+    
+    match cpu {
+        Cpu::Mos6502 | Cpu::Mos6510 => add_undocumented_opcodes(&mut table),
+        Cpu::Cmos65C02 => add_cmos_opcodes(&mut table),
+        Cpu::R65C02 => {
+            add_cmos_opcodes(&mut table);
+            add_rockwell_opcodes(&mut table);
+        }
+        Cpu::W65C02 => {
+            add_cmos_opcodes(&mut table);
+            add_rockwell_opcodes(&mut table);
+            add_wdc_opcodes(&mut table);
+        }
+        Cpu::W65816 => {
+            add_cmos_opcodes(&mut table);
+            add_wdc_opcodes(&mut table);
+            add_65816_opcodes(&mut table);
+        }
+    }
+    table
+}
+
+/// Opcode bytes that decode to an instruction of the processor without
+/// being emitted by the assembler, which uses the canonical byte instead
+pub fn build_decode_only_entries(cpu: Cpu) -> Vec<(Opcode, AddressingMode, OpcodeEntry)> {
+    match cpu {
+        Cpu::Mos6502 | Cpu::Mos6510 => undocumented_duplicates(),
+        _ => Vec::new(),
+    }
+}
+
+/// Documented instructions shared by all processors of the family
+fn add_documented_opcodes(table: &mut OpcodeTable) {
+    
+    // Load/Store Operations
+    // LDA
+    table.insert((Opcode::LDA, AddressingMode::Immediate), OpcodeEntry::new(0xA9, 2, 2));
+    table.insert((Opcode::LDA, AddressingMode::ZeroPage), OpcodeEntry::new(0xA5, 2, 3));
+    table.insert((Opcode::LDA, AddressingMode::ZeroPageX), OpcodeEntry::new(0xB5, 2, 4));
+    table.insert((Opcode::LDA, AddressingMode::Absolute), OpcodeEntry::new(0xAD, 3, 4));
+    table.insert((Opcode::LDA, AddressingMode::AbsoluteX), OpcodeEntry::new(0xBD, 3, 4));
+    table.insert((Opcode::LDA, AddressingMode::AbsoluteY), OpcodeEntry::new(0xB9, 3, 4));
+    table.insert((Opcode::LDA, AddressingMode::IndexedIndirect), OpcodeEntry::new(0xA1, 2, 6));
+    table.insert((Opcode::LDA, AddressingMode::IndirectIndexed), OpcodeEntry::new(0xB1, 2, 5));
+    
+    // LDX
+    table.insert((Opcode::LDX, AddressingMode::Immediate), OpcodeEntry::new(0xA2, 2, 2));
+    table.insert((Opcode::LDX, AddressingMode::ZeroPage), OpcodeEntry::new(0xA6, 2, 3));
+    table.insert((Opcode::LDX, AddressingMode::ZeroPageY), OpcodeEntry::new(0xB6, 2, 4));
+    table.insert((Opcode::LDX, AddressingMode::Absolute), OpcodeEntry::new(0xAE, 3, 4));
+    table.insert((Opcode::LDX, AddressingMode::AbsoluteY), OpcodeEntry::new(0xBE, 3, 4));
+    
+    // LDY
+    table.insert((Opcode::LDY, AddressingMode::Immediate), OpcodeEntry::new(0xA0, 2, 2));
+    table.insert((Opcode::LDY, AddressingMode::ZeroPage), OpcodeEntry::new(0xA4, 2, 3));
+    table.insert((Opcode::LDY, AddressingMode::ZeroPageX), OpcodeEntry::new(0xB4, 2, 4));
+    table.insert((Opcode::LDY, AddressingMode::Absolute), OpcodeEntry::new(0xAC, 3, 4));
+    table.insert((Opcode::LDY, AddressingMode::AbsoluteX), OpcodeEntry::new(0xBC, 3, 4));
+    
+    // STA
+    table.insert((Opcode::STA, AddressingMode::ZeroPage), OpcodeEntry::new(0x85, 2, 3));
+    table.insert((Opcode::STA, AddressingMode::ZeroPageX), OpcodeEntry::new(0x95, 2, 4));
+    table.insert((Opcode::STA, AddressingMode::Absolute), OpcodeEntry::new(0x8D, 3, 4));
+    table.insert((Opcode::STA, AddressingMode::AbsoluteX), OpcodeEntry::new(0x9D, 3, 5));
+    table.insert((Opcode::STA, AddressingMode::AbsoluteY), OpcodeEntry::new(0x99, 3, 5));
+    table.insert((Opcode::STA, AddressingMode::IndexedIndirect), OpcodeEntry::new(0x81, 2, 6));
+    table.insert((Opcode::STA, AddressingMode::IndirectIndexed), OpcodeEntry::new(0x91, 2, 6));
+    
+    // STX
+    table.insert((Opcode::STX, AddressingMode::ZeroPage), OpcodeEntry::new(0x86, 2, 3));
+    table.insert((Opcode::STX, AddressingMode::ZeroPageY), OpcodeEntry::new(0x96, 2, 4));
+    table.insert((Opcode::STX, AddressingMode::Absolute), OpcodeEntry::new(0x8E, 3, 4));
+    
+    // STY
+    table.insert((Opcode::STY, AddressingMode::ZeroPage), OpcodeEntry::new(0x84, 2, 3));
+    table.insert((Opcode::STY, AddressingMode::ZeroPageX), OpcodeEntry::new(0x94, 2, 4));
+    table.insert((Opcode::STY, AddressingMode::Absolute), OpcodeEntry::new(0x8C, 3, 4));
+    
+    // Register Transfers
+    table.insert((Opcode::TAX, AddressingMode::Implied), OpcodeEntry::new(0xAA, 1, 2));
+    table.insert((Opcode::TAY, AddressingMode::Implied), OpcodeEntry::new(0xA8, 1, 2));
+    table.insert((Opcode::TSX, AddressingMode::Implied), OpcodeEntry::new(0xBA, 1, 2));
+    table.insert((Opcode::TXA, AddressingMode::Implied), OpcodeEntry::new(0x8A, 1, 2));
+    table.insert((Opcode::TXS, AddressingMode::Implied), OpcodeEntry::new(0x9A, 1, 2));
+    table.insert((Opcode::TYA, AddressingMode::Implied), OpcodeEntry::new(0x98, 1, 2));
+    
+    // Stack Operations
+    table.insert((Opcode::PHA, AddressingMode::Implied), OpcodeEntry::new(0x48, 1, 3));
+    table.insert((Opcode::PHP, AddressingMode::Implied), OpcodeEntry::new(0x08, 1, 3));
+    table.insert((Opcode::PLA, AddressingMode::Implied), OpcodeEntry::new(0x68, 1, 4));
+    table.insert((Opcode::PLP, AddressingMode::Implied), OpcodeEntry::new(0x28, 1, 4));
+    
+    // Logical Operations
+    // AND
+    table.insert((Opcode::AND, AddressingMode::Immediate), OpcodeEntry::new(0x29, 2, 2));
+    table.insert((Opcode::AND, AddressingMode::ZeroPage), OpcodeEntry::new(0x25, 2, 3));
+    table.insert((Opcode::AND, AddressingMode::ZeroPageX), OpcodeEntry::new(0x35, 2, 4));
+    table.insert((Opcode::AND, AddressingMode::Absolute), OpcodeEntry::new(0x2D, 3, 4));
+    table.insert((Opcode::AND, AddressingMode::AbsoluteX), OpcodeEntry::new(0x3D, 3, 4));
+    table.insert((Opcode::AND, AddressingMode::AbsoluteY), OpcodeEntry::new(0x39, 3, 4));
+    table.insert((Opcode::AND, AddressingMode::IndexedIndirect), OpcodeEntry::new(0x21, 2, 6));
+    table.insert((Opcode::AND, AddressingMode::IndirectIndexed), OpcodeEntry::new(0x31, 2, 5));
+    
+    // EOR
+    table.insert((Opcode::EOR, AddressingMode::Immediate), OpcodeEntry::new(0x49, 2, 2));
+    table.insert((Opcode::EOR, AddressingMode::ZeroPage), OpcodeEntry::new(0x45, 2, 3));
+    table.insert((Opcode::EOR, AddressingMode::ZeroPageX), OpcodeEntry::new(0x55, 2, 4));
+    table.insert((Opcode::EOR, AddressingMode::Absolute), OpcodeEntry::new(0x4D, 3, 4));
+    table.insert((Opcode::EOR, AddressingMode::AbsoluteX), OpcodeEntry::new(0x5D, 3, 4));
+    table.insert((Opcode::EOR, AddressingMode::AbsoluteY), OpcodeEntry::new(0x59, 3, 4));
+    table.insert((Opcode::EOR, AddressingMode::IndexedIndirect), OpcodeEntry::new(0x41, 2, 6));
+    table.insert((Opcode::EOR, AddressingMode::IndirectIndexed), OpcodeEntry::new(0x51, 2, 5));
+    
+    // ORA
+    table.insert((Opcode::ORA, AddressingMode::Immediate), OpcodeEntry::new(0x09, 2, 2));
+    table.insert((Opcode::ORA, AddressingMode::ZeroPage), OpcodeEntry::new(0x05, 2, 3));
+    table.insert((Opcode::ORA, AddressingMode::ZeroPageX), OpcodeEntry::new(0x15, 2, 4));
+    table.insert((Opcode::ORA, AddressingMode::Absolute), OpcodeEntry::new(0x0D, 3, 4));
+    table.insert((Opcode::ORA, AddressingMode::AbsoluteX), OpcodeEntry::new(0x1D, 3, 4));
+    table.insert((Opcode::ORA, AddressingMode::AbsoluteY), OpcodeEntry::new(0x19, 3, 4));
+    table.insert((Opcode::ORA, AddressingMode::IndexedIndirect), OpcodeEntry::new(0x01, 2, 6));
+    table.insert((Opcode::ORA, AddressingMode::IndirectIndexed), OpcodeEntry::new(0x11, 2, 5));
+    
+    // BIT
+    table.insert((Opcode::BIT, AddressingMode::ZeroPage), OpcodeEntry::new(0x24, 2, 3));
+    table.insert((Opcode::BIT, AddressingMode::Absolute), OpcodeEntry::new(0x2C, 3, 4));
+    
+    // Arithmetic Operations
+    // ADC
+    table.insert((Opcode::ADC, AddressingMode::Immediate), OpcodeEntry::new(0x69, 2, 2));
+    table.insert((Opcode::ADC, AddressingMode::ZeroPage), OpcodeEntry::new(0x65, 2, 3));
+    table.insert((Opcode::ADC, AddressingMode::ZeroPageX), OpcodeEntry::new(0x75, 2, 4));
+    table.insert((Opcode::ADC, AddressingMode::Absolute), OpcodeEntry::new(0x6D, 3, 4));
+    table.insert((Opcode::ADC, AddressingMode::AbsoluteX), OpcodeEntry::new(0x7D, 3, 4));
+    table.insert((Opcode::ADC, AddressingMode::AbsoluteY), OpcodeEntry::new(0x79, 3, 4));
+    table.insert((Opcode::ADC, AddressingMode::IndexedIndirect), OpcodeEntry::new(0x61, 2, 6));
+    table.insert((Opcode::ADC, AddressingMode::IndirectIndexed), OpcodeEntry::new(0x71, 2, 5));
+    
+    // SBC
+    table.insert((Opcode::SBC, AddressingMode::Immediate), OpcodeEntry::new(0xE9, 2, 2));
+    table.insert((Opcode::SBC, AddressingMode::ZeroPage), OpcodeEntry::new(0xE5, 2, 3));
+    table.insert((Opcode::SBC, AddressingMode::ZeroPageX), OpcodeEntry::new(0xF5, 2, 4));
+    table.insert((Opcode::SBC, AddressingMode::Absolute), OpcodeEntry::new(0xED, 3, 4));
+    table.insert((Opcode::SBC, AddressingMode::AbsoluteX), OpcodeEntry::new(0xFD, 3, 4));
+    table.insert((Opcode::SBC, AddressingMode::AbsoluteY), OpcodeEntry::new(0xF9, 3, 4));
+    table.insert((Opcode::SBC, AddressingMode::IndexedIndirect), OpcodeEntry::new(0xE1, 2, 6));
+    table.insert((Opcode::SBC, AddressingMode::IndirectIndexed), OpcodeEntry::new(0xF1, 2, 5));
+    
+    // CMP
+    table.insert((Opcode::CMP, AddressingMode::Immediate), OpcodeEntry::new(0xC9, 2, 2));
+    table.insert((Opcode::CMP, AddressingMode::ZeroPage), OpcodeEntry::new(0xC5, 2, 3));
+    table.insert((Opcode::CMP, AddressingMode::ZeroPageX), OpcodeEntry::new(0xD5, 2, 4));
+    table.insert((Opcode::CMP, AddressingMode::Absolute), OpcodeEntry::new(0xCD, 3, 4));
+    table.insert((Opcode::CMP, AddressingMode::AbsoluteX), OpcodeEntry::new(0xDD, 3, 4));
+    table.insert((Opcode::CMP, AddressingMode::AbsoluteY), OpcodeEntry::new(0xD9, 3, 4));
+    table.insert((Opcode::CMP, AddressingMode::IndexedIndirect), OpcodeEntry::new(0xC1, 2, 6));
+    table.insert((Opcode::CMP, AddressingMode::IndirectIndexed), OpcodeEntry::new(0xD1, 2, 5));
+    
+    // CPX
+    table.insert((Opcode::CPX, AddressingMode::Immediate), OpcodeEntry::new(0xE0, 2, 2));
+    table.insert((Opcode::CPX, AddressingMode::ZeroPage), OpcodeEntry::new(0xE4, 2, 3));
+    table.insert((Opcode::CPX, AddressingMode::Absolute), OpcodeEntry::new(0xEC, 3, 4));
+    
+    // CPY
+    table.insert((Opcode::CPY, AddressingMode::Immediate), OpcodeEntry::new(0xC0, 2, 2));
+    table.insert((Opcode::CPY, AddressingMode::ZeroPage), OpcodeEntry::new(0xC4, 2, 3));
+    table.insert((Opcode::CPY, AddressingMode::Absolute), OpcodeEntry::new(0xCC, 3, 4));
+    
+    // Increments & Decrements
+    // INC
+    table.insert((Opcode::INC, AddressingMode::ZeroPage), OpcodeEntry::new(0xE6, 2, 5));
+    table.insert((Opcode::INC, AddressingMode::ZeroPageX), OpcodeEntry::new(0xF6, 2, 6));
+    table.insert((Opcode::INC, AddressingMode::Absolute), OpcodeEntry::new(0xEE, 3, 6));
+    table.insert((Opcode::INC, AddressingMode::AbsoluteX), OpcodeEntry::new(0xFE, 3, 7));
+    
+    // INX
+    table.insert((Opcode::INX, AddressingMode::Implied), OpcodeEntry::new(0xE8, 1, 2));
+    
+    // INY
+    table.insert((Opcode::INY, AddressingMode::Implied), OpcodeEntry::new(0xC8, 1, 2));
+    
+    // DEC
+    table.insert((Opcode::DEC, AddressingMode::ZeroPage), OpcodeEntry::new(0xC6, 2, 5));
+    table.insert((Opcode::DEC, AddressingMode::ZeroPageX), OpcodeEntry::new(0xD6, 2, 6));
+    table.insert((Opcode::DEC, AddressingMode::Absolute), OpcodeEntry::new(0xCE, 3, 6));
+    table.insert((Opcode::DEC, AddressingMode::AbsoluteX), OpcodeEntry::new(0xDE, 3, 7));
+    
+    // DEX
+    table.insert((Opcode::DEX, AddressingMode::Implied), OpcodeEntry::new(0xCA, 1, 2));
+    
+    // DEY
+    table.insert((Opcode::DEY, AddressingMode::Implied), OpcodeEntry::new(0x88, 1, 2));
+    
+    // Shifts
+    // ASL
+    table.insert((Opcode::ASL, AddressingMode::Accumulator), OpcodeEntry::new(0x0A, 1, 2));
+    table.insert((Opcode::ASL, AddressingMode::ZeroPage), OpcodeEntry::new(0x06, 2, 5));
+    table.insert((Opcode::ASL, AddressingMode::ZeroPageX), OpcodeEntry::new(0x16, 2, 6));
+    table.insert((Opcode::ASL, AddressingMode::Absolute), OpcodeEntry::new(0x0E, 3, 6));
+    table.insert((Opcode::ASL, AddressingMode::AbsoluteX), OpcodeEntry::new(0x1E, 3, 7));
+    
+    // LSR
+    table.insert((Opcode::LSR, AddressingMode::Accumulator), OpcodeEntry::new(0x4A, 1, 2));
+    table.insert((Opcode::LSR, AddressingMode::ZeroPage), OpcodeEntry::new(0x46, 2, 5));
+    table.insert((Opcode::LSR, AddressingMode::ZeroPageX), OpcodeEntry::new(0x56, 2, 6));
+    table.insert((Opcode::LSR, AddressingMode::Absolute), OpcodeEntry::new(0x4E, 3, 6));
+    table.insert((Opcode::LSR, AddressingMode::AbsoluteX), OpcodeEntry::new(0x5E, 3, 7));
+    
+    // ROL
+    table.insert((Opcode::ROL, AddressingMode::Accumulator), OpcodeEntry::new(0x2A, 1, 2));
+    table.insert((Opcode::ROL, AddressingMode::ZeroPage), OpcodeEntry::new(0x26, 2, 5));
+    table.insert((Opcode::ROL, AddressingMode::ZeroPageX), OpcodeEntry::new(0x36, 2, 6));
+    table.insert((Opcode::ROL, AddressingMode::Absolute), OpcodeEntry::new(0x2E, 3, 6));
+    table.insert((Opcode::ROL, AddressingMode::AbsoluteX), OpcodeEntry::new(0x3E, 3, 7));
+    
+    // ROR
+    table.insert((Opcode::ROR, AddressingMode::Accumulator), OpcodeEntry::new(0x6A, 1, 2));
+    table.insert((Opcode::ROR, AddressingMode::ZeroPage), OpcodeEntry::new(0x66, 2, 5));
+    table.insert((Opcode::ROR, AddressingMode::ZeroPageX), OpcodeEntry::new(0x76, 2, 6));
+    table.insert((Opcode::ROR, AddressingMode::Absolute), OpcodeEntry::new(0x6E, 3, 6));
+    table.insert((Opcode::ROR, AddressingMode::AbsoluteX), OpcodeEntry::new(0x7E, 3, 7));
+    
+    // Jumps & Calls
+    table.insert((Opcode::JMP, AddressingMode::Absolute), OpcodeEntry::new(0x4C, 3, 3));
+    table.insert((Opcode::JMP, AddressingMode::Indirect), OpcodeEntry::new(0x6C, 3, 5));
+    table.insert((Opcode::JSR, AddressingMode::Absolute), OpcodeEntry::new(0x20, 3, 6));
+    table.insert((Opcode::RTS, AddressingMode::Implied), OpcodeEntry::new(0x60, 1, 6));
+    table.insert((Opcode::RTI, AddressingMode::Implied), OpcodeEntry::new(0x40, 1, 6));
+    table.insert((Opcode::BRK, AddressingMode::Implied), OpcodeEntry::new(0x00, 1, 7));
+    
+    // Branches
+    table.insert((Opcode::BCC, AddressingMode::Relative), OpcodeEntry::new(0x90, 2, 2));
+    table.insert((Opcode::BCS, AddressingMode::Relative), OpcodeEntry::new(0xB0, 2, 2));
+    table.insert((Opcode::BEQ, AddressingMode::Relative), OpcodeEntry::new(0xF0, 2, 2));
+    table.insert((Opcode::BMI, AddressingMode::Relative), OpcodeEntry::new(0x30, 2, 2));
+    table.insert((Opcode::BNE, AddressingMode::Relative), OpcodeEntry::new(0xD0, 2, 2));
+    table.insert((Opcode::BPL, AddressingMode::Relative), OpcodeEntry::new(0x10, 2, 2));
+    table.insert((Opcode::BVC, AddressingMode::Relative), OpcodeEntry::new(0x50, 2, 2));
+    table.insert((Opcode::BVS, AddressingMode::Relative), OpcodeEntry::new(0x70, 2, 2));
+    
+    // Status Flag Changes
+    table.insert((Opcode::CLC, AddressingMode::Implied), OpcodeEntry::new(0x18, 1, 2));
+    table.insert((Opcode::CLD, AddressingMode::Implied), OpcodeEntry::new(0xD8, 1, 2));
+    table.insert((Opcode::CLI, AddressingMode::Implied), OpcodeEntry::new(0x58, 1, 2));
+    table.insert((Opcode::CLV, AddressingMode::Implied), OpcodeEntry::new(0xB8, 1, 2));
+    table.insert((Opcode::SEC, AddressingMode::Implied), OpcodeEntry::new(0x38, 1, 2));
+    table.insert((Opcode::SED, AddressingMode::Implied), OpcodeEntry::new(0xF8, 1, 2));
+    table.insert((Opcode::SEI, AddressingMode::Implied), OpcodeEntry::new(0x78, 1, 2));
+    
+    // No Operation
+    table.insert((Opcode::NOP, AddressingMode::Implied), OpcodeEntry::new(0xEA, 1, 2));
+}
+
+/// Undocumented NMOS opcodes, these are NOPs or different instructions on CMOS parts
+fn add_undocumented_opcodes(table: &mut OpcodeTable) {
+    // Read-modify-write combinations
+    // SLO (ASL + ORA)
+    table.insert((Opcode::SLO, AddressingMode::ZeroPage), OpcodeEntry::new(0x07, 2, 5));
+    table.insert((Opcode::SLO, AddressingMode::ZeroPageX), OpcodeEntry::new(0x17, 2, 6));
+    table.insert((Opcode::SLO, AddressingMode::Absolute), OpcodeEntry::new(0x0F, 3, 6));
+    table.insert((Opcode::SLO, AddressingMode::AbsoluteX), OpcodeEntry::new(0x1F, 3, 7));
+    table.insert((Opcode::SLO, AddressingMode::AbsoluteY), OpcodeEntry::new(0x1B, 3, 7));
+    table.insert((Opcode::SLO, AddressingMode::IndexedIndirect), OpcodeEntry::new(0x03, 2, 8));
+    table.insert((Opcode::SLO, AddressingMode::IndirectIndexed), OpcodeEntry::new(0x13, 2, 8));
+    
+    // RLA (ROL + AND)
+    table.insert((Opcode::RLA, AddressingMode::ZeroPage), OpcodeEntry::new(0x27, 2, 5));
+    table.insert((Opcode::RLA, AddressingMode::ZeroPageX), OpcodeEntry::new(0x37, 2, 6));
+    table.insert((Opcode::RLA, AddressingMode::Absolute), OpcodeEntry::new(0x2F, 3, 6));
+    table.insert((Opcode::RLA, AddressingMode::AbsoluteX), OpcodeEntry::new(0x3F, 3, 7));
+    table.insert((Opcode::RLA, AddressingMode::AbsoluteY), OpcodeEntry::new(0x3B, 3, 7));
+    table.insert((Opcode::RLA, AddressingMode::IndexedIndirect), OpcodeEntry::new(0x23, 2, 8));
+    table.insert((Opcode::RLA, AddressingMode::IndirectIndexed), OpcodeEntry::new(0x33, 2, 8));
+    
+    // SRE (LSR + EOR)
+    table.insert((Opcode::SRE, AddressingMode::ZeroPage), OpcodeEntry::new(0x47, 2, 5));
+    table.insert((Opcode::SRE, AddressingMode::ZeroPageX), OpcodeEntry::new(0x57, 2, 6));
+    table.insert((Opcode::SRE, AddressingMode::Absolute), OpcodeEntry::new(0x4F, 3, 6));
+    table.insert((Opcode::SRE, AddressingMode::AbsoluteX), OpcodeEntry::new(0x5F, 3, 7));
+    table.insert((Opcode::SRE, AddressingMode::AbsoluteY), OpcodeEntry::new(0x5B, 3, 7));
+    table.insert((Opcode::SRE, AddressingMode::IndexedIndirect), OpcodeEntry::new(0x43, 2, 8));
+    table.insert((Opcode::SRE, AddressingMode::IndirectIndexed), OpcodeEntry::new(0x53, 2, 8));
+    
+    // RRA (ROR + ADC)
+    table.insert((Opcode::RRA, AddressingMode::ZeroPage), OpcodeEntry::new(0x67, 2, 5));
+    table.insert((Opcode::RRA, AddressingMode::ZeroPageX), OpcodeEntry::new(0x77, 2, 6));
+    table.insert((Opcode::RRA, AddressingMode::Absolute), OpcodeEntry::new(0x6F, 3, 6));
+    table.insert((Opcode::RRA, AddressingMode::AbsoluteX), OpcodeEntry::new(0x7F, 3, 7));
+    table.insert((Opcode::RRA, AddressingMode::AbsoluteY), OpcodeEntry::new(0x7B, 3, 7));
+    table.insert((Opcode::RRA, AddressingMode::IndexedIndirect), OpcodeEntry::new(0x63, 2, 8));
+    table.insert((Opcode::RRA, AddressingMode::IndirectIndexed), OpcodeEntry::new(0x73, 2, 8));
+    
+    // DCP (DEC + CMP)
+    table.insert((Opcode::DCP, AddressingMode::ZeroPage), OpcodeEntry::new(0xC7, 2, 5));
+    table.insert((Opcode::DCP, AddressingMode::ZeroPageX), OpcodeEntry::new(0xD7, 2, 6));
+    table.insert((Opcode::DCP, AddressingMode::Absolute), OpcodeEntry::new(0xCF, 3, 6));
+    table.insert((Opcode::DCP, AddressingMode::AbsoluteX), OpcodeEntry::new(0xDF, 3, 7));
+    table.insert((Opcode::DCP, AddressingMode::AbsoluteY), OpcodeEntry::new(0xDB, 3, 7));
+    table.insert((Opcode::DCP, AddressingMode::IndexedIndirect), OpcodeEntry::new(0xC3, 2, 8));
+    table.insert((Opcode::DCP, AddressingMode::IndirectIndexed), OpcodeEntry::new(0xD3, 2, 8));
+    
+    // ISC (INC + SBC)
+    table.insert((Opcode::ISC, AddressingMode::ZeroPage), OpcodeEntry::new(0xE7, 2, 5));
+    table.insert((Opcode::ISC, AddressingMode::ZeroPageX), OpcodeEntry::new(0xF7, 2, 6));
+    table.insert((Opcode::ISC, AddressingMode::Absolute), OpcodeEntry::new(0xEF, 3, 6));
+    table.insert((Opcode::ISC, AddressingMode::AbsoluteX), OpcodeEntry::new(0xFF, 3, 7));
+    table.insert((Opcode::ISC, AddressingMode::AbsoluteY), OpcodeEntry::new(0xFB, 3, 7));
+    table.insert((Opcode::ISC, AddressingMode::IndexedIndirect), OpcodeEntry::new(0xE3, 2, 8));
+    table.insert((Opcode::ISC, AddressingMode::IndirectIndexed), OpcodeEntry::new(0xF3, 2, 8));
+    
+    // SAX (store A & X)
+    table.insert((Opcode::SAX, AddressingMode::ZeroPage), OpcodeEntry::new(0x87, 2, 3));
+    table.insert((Opcode::SAX, AddressingMode::ZeroPageY), OpcodeEntry::new(0x97, 2, 4));
+    table.insert((Opcode::SAX, AddressingMode::Absolute), OpcodeEntry::new(0x8F, 3, 4));
+    table.insert((Opcode::SAX, AddressingMode::IndexedIndirect), OpcodeEntry::new(0x83, 2, 6));
+    
+    // LAX (LDA + LDX)
+    table.insert((Opcode::LAX, AddressingMode::ZeroPage), OpcodeEntry::new(0xA7, 2, 3));
+    table.insert((Opcode::LAX, AddressingMode::ZeroPageY), OpcodeEntry::new(0xB7, 2, 4));
+    table.insert((Opcode::LAX, AddressingMode::Absolute), OpcodeEntry::new(0xAF, 3, 4));
+    table.insert((Opcode::LAX, AddressingMode::AbsoluteY), OpcodeEntry::new(0xBF, 3, 4));
+    table.insert((Opcode::LAX, AddressingMode::IndexedIndirect), OpcodeEntry::new(0xA3, 2, 6));
+    table.insert((Opcode::LAX, AddressingMode::IndirectIndexed), OpcodeEntry::new(0xB3, 2, 5));
+    table.insert((Opcode::LAX, AddressingMode::Immediate), OpcodeEntry::new(0xAB, 2, 2).unstable());
+    
+    // Immediate combinations
+    table.insert((Opcode::ANC, AddressingMode::Immediate), OpcodeEntry::new(0x0B, 2, 2));
+    table.insert((Opcode::ALR, AddressingMode::Immediate), OpcodeEntry::new(0x4B, 2, 2));
+    table.insert((Opcode::ARR, AddressingMode::Immediate), OpcodeEntry::new(0x6B, 2, 2));
+    table.insert((Opcode::SBX, AddressingMode::Immediate), OpcodeEntry::new(0xCB, 2, 2));
+    table.insert((Opcode::USBC, AddressingMode::Immediate), OpcodeEntry::new(0xEB, 2, 2));
+    table.insert((Opcode::LAS, AddressingMode::AbsoluteY), OpcodeEntry::new(0xBB, 3, 4));
+    
+    // Unstable opcodes, their results depend on the chip and on analog effects,
+    // LAX #imm is the same opcode and decodes as LXA
+    table.insert((Opcode::ANE, AddressingMode::Immediate), OpcodeEntry::new(0x8B, 2, 2).unstable());
+    table.insert((Opcode::LXA, AddressingMode::Immediate), OpcodeEntry::new(0xAB, 2, 2).unstable().preferred());
+    table.insert((Opcode::SHA, AddressingMode::AbsoluteY), OpcodeEntry::new(0x9F, 3, 5).unstable());
+    table.insert((Opcode::SHA, AddressingMode::IndirectIndexed), OpcodeEntry::new(0x93, 2, 6).unstable());
+    table.insert((Opcode::SHX, AddressingMode::AbsoluteY), OpcodeEntry::new(0x9E, 3, 5).unstable());
+    table.insert((Opcode::SHY, AddressingMode::AbsoluteX), OpcodeEntry::new(0x9C, 3, 5).unstable());
+    table.insert((Opcode::TAS, AddressingMode::AbsoluteY), OpcodeEntry::new(0x9B, 3, 5).unstable());
+    
+    // Multi-byte NOPs (DOP/TOP), the operand is read and ignored
+    table.insert((Opcode::NOP, AddressingMode::Immediate), OpcodeEntry::new(0x80, 2, 2));
+    table.insert((Opcode::NOP, AddressingMode::ZeroPage), OpcodeEntry::new(0x04, 2, 3));
+    table.insert((Opcode::NOP, AddressingMode::ZeroPageX), OpcodeEntry::new(0x14, 2, 4));
+    table.insert((Opcode::NOP, AddressingMode::Absolute), OpcodeEntry::new(0x0C, 3, 4));
+    table.insert((Opcode::NOP, AddressingMode::AbsoluteX), OpcodeEntry::new(0x1C, 3, 4));
+    
+    // JAM halts the CPU until reset
+    table.insert((Opcode::JAM, AddressingMode::Implied), OpcodeEntry::new(0x02, 1, 0));
+}
+
+/// Undocumented NMOS bytes that behave like an opcode of the table
+fn undocumented_duplicates() -> Vec<(Opcode, AddressingMode, OpcodeEntry)> {
+    let mut entries = Vec::new();
+    
+    // JAM
+    for byte in [0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2] {
+        entries.push((Opcode::JAM, AddressingMode::Implied, OpcodeEntry::new(byte, 1, 0)));
+    }
+    
+    // ANC #imm
+    entries.push((Opcode::ANC, AddressingMode::Immediate, OpcodeEntry::new(0x2B, 2, 2)));
+    
+    // One-byte NOPs
+    for byte in [0x1A, 0x3A, 0x5A, 0x7A, 0xDA, 0xFA] {
+        entries.push((Opcode::NOP, AddressingMode::Implied, OpcodeEntry::new(byte, 1, 2)));
+    }
+    
+    // DOP, read and skip one operand byte
+    for byte in [0x82, 0x89, 0xC2, 0xE2] {
+        entries.push((Opcode::NOP, AddressingMode::Immediate, OpcodeEntry::new(byte, 2, 2)));
+    }
+    for byte in [0x44, 0x64] {
+        entries.push((Opcode::NOP, AddressingMode::ZeroPage, OpcodeEntry::new(byte, 2, 3)));
+    }
+    for byte in [0x34, 0x54, 0x74, 0xD4, 0xF4] {
+        entries.push((Opcode::NOP, AddressingMode::ZeroPageX, OpcodeEntry::new(byte, 2, 4)));
+    }
+    
+    // TOP, read and skip a two byte operand
+    for byte in [0x3C, 0x5C, 0x7C, 0xDC, 0xFC] {
+        entries.push((Opcode::NOP, AddressingMode::AbsoluteX, OpcodeEntry::new(byte, 3, 4)));
+    }
+    
+    entries
+}
+
+/// Instructions and addressing modes added by the 65C02
+fn add_cmos_opcodes(table: &mut OpcodeTable) {
+    // Fixed page wrap of JMP (addr) costs a cycle, shifts with abs,X save one
+    table.insert((Opcode::JMP, AddressingMode::Indirect), OpcodeEntry::new(0x6C, 3, 6));
+    table.insert((Opcode::ASL, AddressingMode::AbsoluteX), OpcodeEntry::new(0x1E, 3, 6));
+    table.insert((Opcode::LSR, AddressingMode::AbsoluteX), OpcodeEntry::new(0x5E, 3, 6));
+    table.insert((Opcode::ROL, AddressingMode::AbsoluteX), OpcodeEntry::new(0x3E, 3, 6));
+    table.insert((Opcode::ROR, AddressingMode::AbsoluteX), OpcodeEntry::new(0x7E, 3, 6));
+    
+    // Zero page indirect
+    table.insert((Opcode::ORA, AddressingMode::ZeroPageIndirect), OpcodeEntry::new(0x12, 2, 5));
+    table.insert((Opcode::AND, AddressingMode::ZeroPageIndirect), OpcodeEntry::new(0x32, 2, 5));
+    table.insert((Opcode::EOR, AddressingMode::ZeroPageIndirect), OpcodeEntry::new(0x52, 2, 5));
+    table.insert((Opcode::ADC, AddressingMode::ZeroPageIndirect), OpcodeEntry::new(0x72, 2, 5));
+    table.insert((Opcode::STA, AddressingMode::ZeroPageIndirect), OpcodeEntry::new(0x92, 2, 5));
+    table.insert((Opcode::LDA, AddressingMode::ZeroPageIndirect), OpcodeEntry::new(0xB2, 2, 5));
+    table.insert((Opcode::CMP, AddressingMode::ZeroPageIndirect), OpcodeEntry::new(0xD2, 2, 5));
+    table.insert((Opcode::SBC, AddressingMode::ZeroPageIndirect), OpcodeEntry::new(0xF2, 2, 5));
+    
+    // BIT
+    table.insert((Opcode::BIT, AddressingMode::Immediate), OpcodeEntry::new(0x89, 2, 2));
+    table.insert((Opcode::BIT, AddressingMode::ZeroPageX), OpcodeEntry::new(0x34, 2, 4));
+    table.insert((Opcode::BIT, AddressingMode::AbsoluteX), OpcodeEntry::new(0x3C, 3, 4));
+    
+    // INC/DEC A
+    table.insert((Opcode::INC, AddressingMode::Accumulator), OpcodeEntry::new(0x1A, 1, 2));
+    table.insert((Opcode::DEC, AddressingMode::Accumulator), OpcodeEntry::new(0x3A, 1, 2));
+    
+    // JMP (addr,X)
+    table.insert((Opcode::JMP, AddressingMode::AbsoluteIndexedIndirect), OpcodeEntry::new(0x7C, 3, 6));
+    
+    // BRA
+    table.insert((Opcode::BRA, AddressingMode::Relative), OpcodeEntry::new(0x80, 2, 3));
+    
+    // STZ
+    table.insert((Opcode::STZ, AddressingMode::ZeroPage), OpcodeEntry::new(0x64, 2, 3));
+    table.insert((Opcode::STZ, AddressingMode::ZeroPageX), OpcodeEntry::new(0x74, 2, 4));
+    table.insert((Opcode::STZ, AddressingMode::Absolute), OpcodeEntry::new(0x9C, 3, 4));
+    table.insert((Opcode::STZ, AddressingMode::AbsoluteX), OpcodeEntry::new(0x9E, 3, 5));
+    
+    // Stack Operations
+    table.insert((Opcode::PHX, AddressingMode::Implied), OpcodeEntry::new(0xDA, 1, 3));
+    table.insert((Opcode::PHY, AddressingMode::Implied), OpcodeEntry::new(0x5A, 1, 3));
+    table.insert((Opcode::PLX, AddressingMode::Implied), OpcodeEntry::new(0xFA, 1, 4));
+    table.insert((Opcode::PLY, AddressingMode::Implied), OpcodeEntry::new(0x7A, 1, 4));
+    
+    // TRB/TSB
+    table.insert((Opcode::TRB, AddressingMode::ZeroPage), OpcodeEntry::new(0x14, 2, 5));
+    table.insert((Opcode::TRB, AddressingMode::Absolute), OpcodeEntry::new(0x1C, 3, 6));
+    table.insert((Opcode::TSB, AddressingMode::ZeroPage), OpcodeEntry::new(0x04, 2, 5));
+    table.insert((Opcode::TSB, AddressingMode::Absolute), OpcodeEntry::new(0x0C, 3, 6));
+}
+
+/// Rockwell bit manipulation and bit branch instructions, one opcode per bit
+fn add_rockwell_opcodes(table: &mut OpcodeTable) {
+    let rmb = [Opcode::RMB0, Opcode::RMB1, Opcode::RMB2, Opcode::RMB3, Opcode::RMB4, Opcode::RMB5, Opcode::RMB6, Opcode::RMB7];
+    let smb = [Opcode::SMB0, Opcode::SMB1, Opcode::SMB2, Opcode::SMB3, Opcode::SMB4, Opcode::SMB5, Opcode::SMB6, Opcode::SMB7];
+    let bbr = [Opcode::BBR0, Opcode::BBR1, Opcode::BBR2, Opcode::BBR3, Opcode::BBR4, Opcode::BBR5, Opcode::BBR6, Opcode::BBR7];
+    let bbs = [Opcode::BBS0, Opcode::BBS1, Opcode::BBS2, Opcode::BBS3, Opcode::BBS4, Opcode::BBS5, Opcode::BBS6, Opcode::BBS7];
+    
+    for bit in 0..8 {
+        let row = (bit as u8) << 4;
+        table.insert((rmb[bit], AddressingMode::ZeroPage), OpcodeEntry::new(0x07 | row, 2, 5));
+        table.insert((smb[bit], AddressingMode::ZeroPage), OpcodeEntry::new(0x87 | row, 2, 5));
+        table.insert((bbr[bit], AddressingMode::ZeroPageRelative), OpcodeEntry::new(0x0F | row, 3, 5));
+        table.insert((bbs[bit], AddressingMode::ZeroPageRelative), OpcodeEntry::new(0x8F | row, 3, 5));
+    }
+}
+
+/// Low power instructions of the WDC parts
+fn add_wdc_opcodes(table: &mut OpcodeTable) {
+    table.insert((Opcode::WAI, AddressingMode::Implied), OpcodeEntry::new(0xCB, 1, 3));
+    table.insert((Opcode::STP, AddressingMode::Implied), OpcodeEntry::new(0xDB, 1, 3));
+}
+
+/// Instructions and addressing modes added by the 65816, cycles are given
+/// for 8-bit registers
+fn add_65816_opcodes(table: &mut OpcodeTable) {
+    // Immediates follow the width of the accumulator or index registers
+    for (opcode, byte) in [
+        (Opcode::ORA, 0x09), (Opcode::AND, 0x29), (Opcode::EOR, 0x49), (Opcode::ADC, 0x69),
+        (Opcode::BIT, 0x89), (Opcode::LDA, 0xA9), (Opcode::CMP, 0xC9), (Opcode::SBC, 0xE9),
+    ] {
+        table.insert((opcode, AddressingMode::Immediate), OpcodeEntry::new(byte, 2, 2).sized_by(Register::Accumulator));
+    }
+    for (opcode, byte) in [
+        (Opcode::LDY, 0xA0), (Opcode::LDX, 0xA2), (Opcode::CPY, 0xC0), (Opcode::CPX, 0xE0),
+    ] {
+        table.insert((opcode, AddressingMode::Immediate), OpcodeEntry::new(byte, 2, 2).sized_by(Register::Index));
+    }
+    
+    // Long, stack relative and indirect long forms of the accumulator
+    // instructions, one column per mode in each opcode row
+    for (opcode, row) in [
+        (Opcode::ORA, 0x00), (Opcode::AND, 0x20), (Opcode::EOR, 0x40), (Opcode::ADC, 0x60),
+        (Opcode::STA, 0x80), (Opcode::LDA, 0xA0), (Opcode::CMP, 0xC0), (Opcode::SBC, 0xE0),
+    ] {
+        table.insert((opcode, AddressingMode::StackRelative), OpcodeEntry::new(row | 0x03, 2, 4));
+        table.insert((opcode, AddressingMode::ZeroPageIndirectLong), OpcodeEntry::new(row | 0x07, 2, 6));
+        table.insert((opcode, AddressingMode::AbsoluteLong), OpcodeEntry::new(row | 0x0F, 4, 5));
+        table.insert((opcode, AddressingMode::StackRelativeIndirectIndexed), OpcodeEntry::new(row | 0x13, 2, 7));
+        table.insert((opcode, AddressingMode::ZeroPageIndirectLongIndexed), OpcodeEntry::new(row | 0x17, 2, 6));
+        table.insert((opcode, AddressingMode::AbsoluteLongX), OpcodeEntry::new(row | 0x1F, 4, 5));
+    }
+    
+    // Jumps & Calls, the long forms of JMP and JSR decode as JML and JSL
+    table.insert((Opcode::JMP, AddressingMode::AbsoluteLong), OpcodeEntry::new(0x5C, 4, 4));
+    table.insert((Opcode::JMP, AddressingMode::AbsoluteIndirectLong), OpcodeEntry::new(0xDC, 3, 6));
+    table.insert((Opcode::JML, AddressingMode::AbsoluteLong), OpcodeEntry::new(0x5C, 4, 4).preferred());
+    table.insert((Opcode::JML, AddressingMode::AbsoluteIndirectLong), OpcodeEntry::new(0xDC, 3, 6).preferred());
+    table.insert((Opcode::JSR, AddressingMode::AbsoluteLong), OpcodeEntry::new(0x22, 4, 8));
+    table.insert((Opcode::JSR, AddressingMode::AbsoluteIndexedIndirect), OpcodeEntry::new(0xFC, 3, 8));
+    table.insert((Opcode::JSL, AddressingMode::AbsoluteLong), OpcodeEntry::new(0x22, 4, 8).preferred());
+    table.insert((Opcode::RTL, AddressingMode::Implied), OpcodeEntry::new(0x6B, 1, 6));
+    table.insert((Opcode::BRL, AddressingMode::RelativeLong), OpcodeEntry::new(0x82, 3, 4));
+    
+    // Block moves, cycles per byte moved
+    table.insert((Opcode::MVN, AddressingMode::BlockMove), OpcodeEntry::new(0x54, 3, 7));
+    table.insert((Opcode::MVP, AddressingMode::BlockMove), OpcodeEntry::new(0x44, 3, 7));
+    
+    // Status Flag Changes
+    table.insert((Opcode::REP, AddressingMode::Immediate), OpcodeEntry::new(0xC2, 2, 3));
+    table.insert((Opcode::SEP, AddressingMode::Immediate), OpcodeEntry::new(0xE2, 2, 3));
+    table.insert((Opcode::XCE, AddressingMode::Implied), OpcodeEntry::new(0xFB, 1, 2));
+    
+    // Stack Operations
+    table.insert((Opcode::PEA, AddressingMode::Absolute), OpcodeEntry::new(0xF4, 3, 5));
+    table.insert((Opcode::PEI, AddressingMode::ZeroPageIndirect), OpcodeEntry::new(0xD4, 2, 6));
+    table.insert((Opcode::PER, AddressingMode::RelativeLong), OpcodeEntry::new(0x62, 3, 6));
+    table.insert((Opcode::PHB, AddressingMode::Implied), OpcodeEntry::new(0x8B, 1, 3));
+    table.insert((Opcode::PHD, AddressingMode::Implied), OpcodeEntry::new(0x0B, 1, 4));
+    table.insert((Opcode::PHK, AddressingMode::Implied), OpcodeEntry::new(0x4B, 1, 3));
+    table.insert((Opcode::PLB, AddressingMode::Implied), OpcodeEntry::new(0xAB, 1, 4));
+    table.insert((Opcode::PLD, AddressingMode::Implied), OpcodeEntry::new(0x2B, 1, 5));
+    
+    // Register Transfers
+    table.insert((Opcode::TCD, AddressingMode::Implied), OpcodeEntry::new(0x5B, 1, 2));
+    table.insert((Opcode::TCS, AddressingMode::Implied), OpcodeEntry::new(0x1B, 1, 2));
+    table.insert((Opcode::TDC, AddressingMode::Implied), OpcodeEntry::new(0x7B, 1, 2));
+    table.insert((Opcode::TSC, AddressingMode::Implied), OpcodeEntry::new(0x3B, 1, 2));
+    table.insert((Opcode::TXY, AddressingMode::Implied), OpcodeEntry::new(0x9B, 1, 2));
+    table.insert((Opcode::TYX, AddressingMode::Implied), OpcodeEntry::new(0xBB, 1, 2));
+    table.insert((Opcode::XBA, AddressingMode::Implied), OpcodeEntry::new(0xEB, 1, 3));
+    
+    // Co-processor and reserved, both take a signature byte
+    table.insert((Opcode::COP, AddressingMode::Immediate), OpcodeEntry::new(0x02, 2, 7));
+    table.insert((Opcode::WDM, AddressingMode::Immediate), OpcodeEntry::new(0x42, 2, 2));
+}
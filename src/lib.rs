@@ -2,11 +2,18 @@
 
 pub mod parser;
 pub mod ast;
+pub mod isa;
+pub mod encoding;
 pub mod assembler;
+pub mod listing;
+pub mod output;
+pub mod symbols;
 
 // Re-export main functions for easier access
-pub use crate::parser::parse_source;
-pub use crate::assembler::assemble;
+pub use crate::parser::{parse_source, parse_source_named};
+pub use crate::assembler::{assemble, assemble_with_format};
+pub use crate::output::OutputFormat;
+pub use crate::encoding::Encoding;
 use crate::ast::Ast;
 
 /// Result type for the assembler operations
@@ -0,0 +1,104 @@
+// Listing file generation for assembled programs
+
+use std::collections::BTreeMap;
+
+use crate::ast::Span;
+
+/// Number of bytes shown per listing row, longer output continues on the following rows
+const BYTES_PER_ROW: usize = 4;
+
+/// Output of a single statement, recorded by the assembler's final pass
+#[derive(Debug, Clone)]
+pub struct ListingEntry {
+    /// Location of the statement in the source
+    pub span: Span,
+    
+    /// Address of the first emitted byte
+    pub address: usize,
+    
+    /// Bytes emitted for the statement
+    pub bytes: Vec<u8>,
+    
+    /// Base cycle count for instructions
+    pub cycles: Option<u8>,
+}
+
+/// Format a listing of `source` (read from `file`) with the recorded entries
+/// 
+/// Each row shows the line number, address, emitted bytes, cycle count and
+/// the original source line. Entries from other files, e.g. statements
+/// added on the command line, are listed before the first source line.
+pub fn format_listing(source: &str, file: &str, entries: &[ListingEntry]) -> String {
+    let mut lines: BTreeMap<usize, Vec<&ListingEntry>> = BTreeMap::new();
+    let mut foreign = Vec::new();
+    for entry in entries {
+        if entry.span.file == file {
+            lines.entry(entry.span.line).or_default().push(entry);
+        } else {
+            foreign.push(entry);
+        }
+    }
+    
+    let mut out = String::new();
+    for entry in foreign {
+        write_rows(&mut out, None, Some(entry), "");
+    }
+    
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        match lines.get(&line) {
+            Some(line_entries) => {
+                // A label shares its address with the statement following it on
+                // the same line, so only label-only lines list the label itself
+                let mut line_entries = line_entries.iter().peekable();
+                let mut first = true;
+                while let Some(entry) = line_entries.next() {
+                    if entry.bytes.is_empty() && entry.cycles.is_none() && line_entries.peek().is_some() {
+                        continue;
+                    }
+                    if first {
+                        write_rows(&mut out, Some(line), Some(entry), text);
+                        first = false;
+                    } else {
+                        write_rows(&mut out, None, Some(entry), "");
+                    }
+                }
+            }
+            None => write_rows(&mut out, Some(line), None, text),
+        }
+    }
+    
+    out
+}
+
+/// Write the rows for one entry, continuing long byte sequences on extra rows
+fn write_rows(out: &mut String, line: Option<usize>, entry: Option<&ListingEntry>, text: &str) {
+    let line = line.map(|l| format!("{:5}", l)).unwrap_or_else(|| " ".repeat(5));
+    
+    let Some(entry) = entry else {
+        push_row(out, format!("{}  {:4}  {:width$}  {:3}  {}", line, "", "", "", text, width = BYTES_PER_ROW * 3));
+        return;
+    };
+    
+    let cycles = entry.cycles.map(|c| c.to_string()).unwrap_or_default();
+    let mut chunks = entry.bytes.chunks(BYTES_PER_ROW);
+    
+    let first = chunks.next().map(hex_bytes).unwrap_or_default();
+    push_row(out, format!("{}  {:04X}  {:width$}  {:>3}  {}", line, entry.address, first, cycles, text, width = BYTES_PER_ROW * 3));
+    
+    for (i, chunk) in chunks.enumerate() {
+        let address = entry.address + (i + 1) * BYTES_PER_ROW;
+        push_row(out, format!("{}  {:04X}  {}", " ".repeat(5), address, hex_bytes(chunk)));
+    }
+}
+
+/// Append a row without trailing whitespace
+fn push_row(out: &mut String, row: String) {
+    out.push_str(row.trim_end());
+    out.push('\n');
+}
+
+/// Format bytes as space separated hex values
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X} ", b)).collect()
+}
@@ -2,7 +2,13 @@ use std::fs;
 use std::path::PathBuf;
 use std::process;
 use clap::{Parser, Subcommand};
-use rusm::{parse_source, assemble, assemble_verbose};
+use rusm::{parse_source_named, OutputFormat};
+use rusm::parser::parse_expression;
+use rusm::assembler::Assembler;
+use rusm::isa::Cpu;
+use rusm::listing::format_listing;
+use rusm::symbols::SymbolFormat;
+use rusm::ast::{Directive, DirectiveArg, Expr, Span, Statement};
 
 #[derive(Parser)]
 #[command(name = "rusm")]
@@ -19,11 +25,47 @@ enum Commands {
         /// Input assembly file
         #[arg(required = true)]
         input: PathBuf,
-
-        /// Output binary file [default: input filename with .prg extension]
+        
+        /// Output binary file [default: input filename with extension of the format]
         #[arg(short, long)]
         output: Option<PathBuf>,
         
+        /// Output format: prg (with load address) or raw [default: from output extension, else prg]
+        #[arg(short, long)]
+        format: Option<OutputFormat>,
+        
+        /// Write a listing with addresses, bytes, cycles and source to this file
+        #[arg(short, long, value_name = "FILE")]
+        listing: Option<PathBuf>,
+        
+        /// Write the symbol table (labels and constants) to this file
+        #[arg(short, long, value_name = "FILE")]
+        symbols: Option<PathBuf>,
+        
+        /// Symbol table format: vice (monitor labels) or json [default: from symbol file extension, else vice]
+        #[arg(long, value_name = "FORMAT")]
+        symbol_format: Option<SymbolFormat>,
+        
+        /// Prepend a BASIC "10 SYS <entry>" autostart line at $0801 [default entry: first instruction after the stub]
+        #[arg(short, long, value_name = "ENTRY", num_args = 0..=1, require_equals = true)]
+        basic_stub: Option<Option<String>>,
+        
+        /// Processor to assemble for: 6502, 6510, 65c02, r65c02, w65c02 or 65816 [default: 6510]
+        #[arg(long, value_name = "CPU")]
+        cpu: Option<Cpu>,
+        
+        /// Infer 65816 register widths from REP/SEP instructions, like .smart
+        #[arg(long)]
+        smart: bool,
+        
+        /// Turn branches whose target is out of reach into a branch over a JMP, like jeq/jne/...
+        #[arg(long)]
+        relax_branches: bool,
+        
+        /// Use unstable undocumented opcodes (ANE, LXA, SHA, ...) without warnings
+        #[arg(long)]
+        allow_unstable: bool,
+        
         /// Enable verbose output
         #[arg(short, long)]
         verbose: bool,
@@ -38,16 +80,25 @@ enum Commands {
 
 fn main() {
     let cli = Cli::parse();
-
+    
     match cli.command {
-        Commands::Assemble { input, output, verbose } => {
+        Commands::Assemble { input, output, format, listing, symbols, symbol_format, basic_stub, cpu, smart, relax_branches, allow_unstable, verbose } => {
+            let format = format.unwrap_or_else(|| {
+                output.as_deref().map(OutputFormat::from_path).unwrap_or_default()
+            });
             let output_path = output.unwrap_or_else(|| {
                 let mut path = input.clone();
-                path.set_extension("prg");
+                path.set_extension(format.extension());
                 path
             });
-
-            match assemble_file(&input, &output_path, verbose) {
+            let symbols = symbols.map(|path| {
+                let format = symbol_format.unwrap_or_else(|| SymbolFormat::from_path(&path));
+                (path, format)
+            });
+            
+            let cpu = cpu.unwrap_or_default();
+            let options = AssembleOptions { format, listing, symbols, basic_stub, cpu, smart, relax_branches, allow_unstable, verbose };
+            match assemble_file(&input, &output_path, options) {
                 Ok(_) => {
                     println!("Successfully assembled {} to {}", 
                         input.display(), output_path.display());
@@ -72,27 +123,69 @@ fn main() {
     }
 }
 
-fn assemble_file(input_path: &PathBuf, output_path: &PathBuf, verbose: bool) -> rusm::Result<()> {
+/// Settings of the assemble command besides the input and output files
+struct AssembleOptions {
+    format: OutputFormat,
+    listing: Option<PathBuf>,
+    symbols: Option<(PathBuf, SymbolFormat)>,
+    basic_stub: Option<Option<String>>,
+    cpu: Cpu,
+    smart: bool,
+    relax_branches: bool,
+    allow_unstable: bool,
+    verbose: bool,
+}
+
+fn assemble_file(input_path: &PathBuf, output_path: &PathBuf, options: AssembleOptions) -> rusm::Result<()> {
+    let AssembleOptions { format, listing, symbols, basic_stub, cpu, smart, relax_branches, allow_unstable, verbose } = options;
     let source = fs::read_to_string(input_path)?;
-    let ast = parse_source(&source)?;
+    let file_name = input_path.display().to_string();
+    let mut ast = parse_source_named(&source, &file_name)?;
+    
+    if let Some(entry) = basic_stub {
+        let args = match entry {
+            Some(entry) => {
+                let entry = parse_expression(&entry)?;
+                vec![DirectiveArg::Expr(Expr::Number(10)), DirectiveArg::Expr(entry)]
+            }
+            None => Vec::new(),
+        };
+        let directive = Directive::new("basicstub", args).with_span(Span::new("<command line>", 0, 0));
+        // After a leading .org, so the stub starts at the program's origin
+        ast.insert_statement(ast.start_index(), Statement::Directive(directive));
+    }
     
     if verbose {
         println!("Parsed AST:");
         println!("{:#?}", ast);
     }
     
-    let binary = if verbose {
-        assemble_verbose(&ast)?
-    } else {
-        assemble(&ast)?
-    };
+    let mut assembler = Assembler::new()
+        .verbose(verbose)
+        .cpu(cpu)
+        .smart(smart)
+        .relax_branches(relax_branches)
+        .allow_unstable(allow_unstable);
+    let output = assembler.assemble_with_format(&ast, format)?;
+    
+    for warning in assembler.warnings() {
+        eprintln!("{}", warning);
+    }
     
     if verbose {
-        println!("Generated {} bytes of machine code", binary.len());
-        print_binary_dump(&binary, 16);
+        println!("Generated {} bytes of {} output at ${:04X}", output.len(), format, assembler.origin());
+        print_binary_dump(&output, 16);
     }
     
-    fs::write(output_path, binary)?;
+    fs::write(output_path, output)?;
+    
+    if let Some(listing_path) = listing {
+        fs::write(listing_path, format_listing(&source, &file_name, assembler.listing()))?;
+    }
+    
+    if let Some((symbols_path, symbol_format)) = symbols {
+        fs::write(symbols_path, symbol_format.format(&assembler.symbols()))?;
+    }
     Ok(())
 }
 
@@ -125,7 +218,7 @@ fn print_binary_dump(data: &[u8], bytes_per_line: usize) {
 
 fn parse_file(input_path: &PathBuf) -> rusm::Result<()> {
     let source = fs::read_to_string(input_path)?;
-    let ast = parse_source(&source)?;
+    let ast = parse_source_named(&source, &input_path.display().to_string())?;
     println!("{:#?}", ast);
     Ok(())
 }
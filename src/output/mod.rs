@@ -0,0 +1,80 @@
+// Output file formats for assembled programs
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Format of the assembled output file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Plain machine code without any header
+    Raw,
+    
+    /// C64 program file: two-byte little-endian load address followed by the code
+    #[default]
+    Prg,
+}
+
+impl OutputFormat {
+    /// Guess the output format from a file extension (`.prg` or anything else)
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("prg") => OutputFormat::Prg,
+            _ => OutputFormat::Raw,
+        }
+    }
+    
+    /// Default file extension for the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Raw => "bin",
+            OutputFormat::Prg => "prg",
+        }
+    }
+    
+    /// Reject origins the format cannot record, a PRG file only has room
+    /// for a 16-bit load address
+    pub fn check_origin(self, origin: usize) -> Result<(), String> {
+        match self {
+            OutputFormat::Prg if origin > 0xFFFF => Err(format!(
+                "Origin ${:06X} does not fit the load address of a prg file, use the raw format", origin
+            )),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Produce the file contents for a binary assembled at `origin`
+    pub fn encode(self, origin: usize, binary: &[u8]) -> Vec<u8> {
+        match self {
+            OutputFormat::Raw => binary.to_vec(),
+            OutputFormat::Prg => {
+                let mut data = Vec::with_capacity(binary.len() + 2);
+                data.push((origin & 0xFF) as u8);
+                data.push(((origin >> 8) & 0xFF) as u8);
+                data.extend_from_slice(binary);
+                data
+            }
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "raw" | "bin" => Ok(OutputFormat::Raw),
+            "prg" => Ok(OutputFormat::Prg),
+            _ => Err(format!("Unknown output format: {} (expected raw or prg)", s)),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Raw => write!(f, "raw"),
+            OutputFormat::Prg => write!(f, "prg"),
+        }
+    }
+}
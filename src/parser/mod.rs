@@ -2,11 +2,14 @@
 
 mod grammar;
 
+use once_cell::sync::Lazy;
 use pest::iterators::{Pair, Pairs};
 use pest::error::Error as PestError;
+use pest::pratt_parser::{Assoc, Op, PrattParser};
 use grammar::{AssemblyParser, Parser, Rule};
 
-use crate::ast::{Ast, Instruction, Opcode, Operand, Label, Directive};
+use crate::encoding::control_code;
+use crate::ast::{AddressWidth, Ast, BinaryOp, Constant, Directive, DirectiveArg, Expr, Text, TextChar, Instruction, Label, Operand, Span, Statement, UnaryOp};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ParseError {
@@ -15,22 +18,35 @@ pub enum ParseError {
     
     #[error("Invalid syntax: {0}")]
     InvalidSyntax(String),
-    
-    #[error("Unknown opcode: {0}")]
-    UnknownOpcode(String),
 }
 
+/// Name used in source locations when parsing source without a file
+const ANONYMOUS_SOURCE: &str = "<source>";
+
 /// Parse source code into AST
 pub fn parse_source(source: &str) -> Result<Ast, ParseError> {
+    parse_source_named(source, ANONYMOUS_SOURCE)
+}
+
+/// Parse source code read from `file` into AST, recording `file` in all spans
+pub fn parse_source_named(source: &str, file: &str) -> Result<Ast, ParseError> {
     let pairs = AssemblyParser::parse(Rule::program, source)
-        .map_err(|e| ParseError::Pest(Box::new(e)))?;
+        .map_err(|e| ParseError::Pest(Box::new(e.with_path(file))))?;
     
     let mut ast = Ast::new();
-    parse_program(pairs, &mut ast)?;
+    parse_program(pairs, file, &mut ast)?;
     Ok(ast)
 }
 
-fn parse_program(pairs: Pairs<Rule>, ast: &mut Ast) -> Result<(), ParseError> {
+/// Source location of the start of a pair
+fn span_of(pair: &Pair<Rule>, file: &str) -> Span {
+    let (line, column) = pair.as_span().start_pos().line_col();
+    Span::new(file, line, column)
+}
+
+fn parse_program(pairs: Pairs<Rule>, file: &str, ast: &mut Ast) -> Result<(), ParseError> {
+    let mut labels = LabelState::default();
+    
     for pair in pairs {
         match pair.as_rule() {
             Rule::program => {
@@ -38,10 +54,9 @@ fn parse_program(pairs: Pairs<Rule>, ast: &mut Ast) -> Result<(), ParseError> {
                 for inner_pair in pair.into_inner() {
                     match inner_pair.as_rule() {
                         Rule::line => {
-                            parse_line(inner_pair.into_inner(), ast)?;
+                            parse_line(inner_pair.into_inner(), file, &mut labels, ast)?;
                         }
                         Rule::EOI => {}, // End of input
-                        Rule::COMMENT => {}, // Ignore top-level comments
                         _ => return Err(ParseError::InvalidSyntax(format!("Unexpected rule in program: {:?}", inner_pair.as_rule())))
                     }
                 }
@@ -53,77 +68,285 @@ fn parse_program(pairs: Pairs<Rule>, ast: &mut Ast) -> Result<(), ParseError> {
     Ok(())
 }
 
-fn parse_line(pairs: Pairs<Rule>, ast: &mut Ast) -> Result<(), ParseError> {
-    let mut label = None;
-    let mut instruction = None;
-    
+fn parse_line(pairs: Pairs<Rule>, file: &str, labels: &mut LabelState, ast: &mut Ast) -> Result<(), ParseError> {
+    // Statements are added in the order they appear, so a label always
+    // precedes the instruction or directive on the same line
     for pair in pairs {
+        let span = span_of(&pair, file);
         match pair.as_rule() {
             Rule::label => {
                 let label_name = pair.as_str().trim_end_matches(':');
-                label = Some(Label::new(label_name));
+                let label_name = if is_local(label_name) {
+                    qualify(label_name, labels.scope()).map_err(|e| located(e, &span))?
+                } else {
+                    check_symbol_name(label_name).map_err(|e| located(e, &span))?;
+                    labels.global = Some(label_name.to_string());
+                    label_name.to_string()
+                };
+                ast.add_label(Label::new(&label_name).with_span(span));
+            }
+            Rule::anonymous_label => {
+                // A `:` label can be reached from both directions, so it is
+                // entered in both sequences
+                let text = pair.as_str();
+                if text == "-" || text == ":" {
+                    ast.add_label(Label::new(&format!("-{}", labels.backward)).with_span(span.clone()));
+                    labels.backward += 1;
+                }
+                if text == "+" || text == ":" {
+                    ast.add_label(Label::new(&format!("+{}", labels.forward)).with_span(span));
+                    labels.forward += 1;
+                }
             }
             Rule::instruction => {
-                instruction = Some(parse_instruction(pair)?);
+                let instruction = parse_instruction(pair, span, labels.scope())?;
+                ast.add_instruction(instruction);
             }
             Rule::directive => {
-                let directive = parse_directive(pair)?;
-                ast.add_directive(directive);
+                let directive = parse_directive(pair, labels.scope()).map_err(|e| located(e, &span))?;
+                ast.add_directive(directive.with_span(span));
             }
             Rule::constant => {
-                let constant = parse_constant(pair)?;
-                ast.add_constant(constant.0, constant.1);
+                let constant = parse_constant(pair, labels.scope()).map_err(|e| located(e, &span))?;
+                ast.add_statement(Statement::Constant(constant.with_span(span)));
+            }
+            Rule::pc_assignment => {
+                // `* = $0801` is another spelling of `.org $0801`
+                let value_pair = pair.into_inner().next().ok_or_else(|| ParseError::InvalidSyntax("Missing origin".to_string()))?;
+                let value = parse_expr(value_pair.into_inner(), labels.scope()).map_err(|e| located(e, &span))?;
+                ast.add_directive(Directive::new("org", vec![DirectiveArg::Expr(value)]).with_span(span));
             }
-            Rule::COMMENT => {}, // Ignore comments
             _ => return Err(ParseError::InvalidSyntax(format!("Unexpected rule in line: {:?}", pair.as_rule())))
         }
     }
     
-    if let Some(l) = label {
-        ast.add_label(l);
-    }
+    Ok(())
+}
+
+/// Labels defined so far, needed to resolve local and anonymous references
+#[derive(Default)]
+struct LabelState {
+    /// The most recent global label
+    global: Option<String>,
     
-    if let Some(i) = instruction {
-        ast.add_instruction(i);
-    }
+    /// Number of anonymous labels reachable backwards (`-` and `:`)
+    backward: usize,
     
+    /// Number of anonymous labels reachable forwards (`+` and `:`)
+    forward: usize,
+}
+
+impl LabelState {
+    fn scope(&self) -> Scope<'_> {
+        Scope {
+            global: self.global.as_deref(),
+            backward: self.backward,
+            forward: self.forward,
+        }
+    }
+}
+
+/// Snapshot of the labels visible from a statement
+#[derive(Debug, Clone, Copy, Default)]
+struct Scope<'a> {
+    global: Option<&'a str>,
+    backward: usize,
+    forward: usize,
+}
+
+/// Reject names that would be ambiguous in operands
+fn check_symbol_name(name: &str) -> Result<(), ParseError> {
+    if name.eq_ignore_ascii_case("a") {
+        return Err(ParseError::InvalidSyntax(format!(
+            "{} is the accumulator and cannot be used as a symbol name", name
+        )));
+    }
     Ok(())
 }
 
-fn parse_instruction(pair: Pair<Rule>) -> Result<Instruction, ParseError> {
+/// Whether a label name is local (`.loop` or `@loop`)
+fn is_local(name: &str) -> bool {
+    name.starts_with(['.', '@'])
+}
+
+/// Qualify a local label name with the enclosing global label, `.loop`
+/// after `start:` becomes `start.loop`
+fn qualify(name: &str, scope: Scope) -> Result<String, ParseError> {
+    match scope.global {
+        Some(scope) => Ok(format!("{}.{}", scope, &name[1..])),
+        None => Err(ParseError::InvalidSyntax(format!(
+            "Local label {} without a preceding global label", name
+        ))),
+    }
+}
+
+/// Resolve an anonymous label reference (`-`, `--`, `+`, `++`) to the name
+/// of the label it points to
+/// 
+/// Anonymous labels are numbered per direction, `-` is the most recent
+/// backward label and `+` the next forward label. Forward references are
+/// checked once all labels are known, when the assembler resolves them.
+fn anonymous_reference(reference: &str, scope: Scope) -> Result<String, ParseError> {
+    let count = reference.len();
+    if reference.starts_with('-') {
+        match scope.backward.checked_sub(count) {
+            Some(index) => Ok(format!("-{}", index)),
+            None => Err(ParseError::InvalidSyntax(format!(
+                "No anonymous label for '{}' before this line", reference
+            ))),
+        }
+    } else {
+        Ok(format!("+{}", scope.forward + count - 1))
+    }
+}
+
+/// Prefix a syntax error with the location of the statement it occurred in
+fn located(error: ParseError, span: &Span) -> ParseError {
+    match error {
+        ParseError::InvalidSyntax(message) => ParseError::InvalidSyntax(format!("{}: {}", span, message)),
+        error => error,
+    }
+}
+
+fn parse_instruction(pair: Pair<Rule>, span: Span, scope: Scope) -> Result<Instruction, ParseError> {
     let mut inner = pair.into_inner();
     
-    let opcode_pair = inner.next().ok_or_else(|| ParseError::InvalidSyntax("Missing opcode".to_string()))?;
-    if opcode_pair.as_rule() != Rule::opcode {
-        return Err(ParseError::InvalidSyntax(format!("Expected opcode, got {:?}", opcode_pair.as_rule())));
+    let mnemonic_pair = inner.next().ok_or_else(|| ParseError::InvalidSyntax("Missing opcode".to_string()))?;
+    if mnemonic_pair.as_rule() != Rule::mnemonic {
+        return Err(ParseError::InvalidSyntax(format!("Expected mnemonic, got {:?}", mnemonic_pair.as_rule())));
     }
     
+    // Mnemonics are resolved by the assembler, with the instruction set
+    // selected where the instruction is
+    let mut mnemonic = mnemonic_pair.into_inner();
+    let opcode_pair = mnemonic.next().ok_or_else(|| ParseError::InvalidSyntax("Missing opcode".to_string()))?;
     let opcode_str = opcode_pair.as_str().to_uppercase();
-    let opcode = Opcode::from_str(&opcode_str)
-        .map_err(|_| ParseError::UnknownOpcode(opcode_str))?;
+    
+    let width = match mnemonic.next() {
+        Some(suffix) => parse_width_suffix(suffix.as_str())?,
+        None => AddressWidth::Auto,
+    };
+    
+    if BIT_MNEMONICS.contains(&opcode_str.as_str()) && width == AddressWidth::Auto {
+        return parse_bit_instruction(&opcode_str, inner.next(), span, scope);
+    }
     
     let operand = if let Some(next_pair) = inner.next() {
         if next_pair.as_rule() == Rule::operand {
-            Some(parse_operand(next_pair)?)
-        } else if next_pair.as_rule() == Rule::COMMENT {
-            // Ignore comments
-            None
+            Some(parse_operand(next_pair, width, &span, scope)?)
         } else {
             return Err(ParseError::InvalidSyntax(format!("Expected operand, got {:?}", next_pair.as_rule())));
         }
+    } else if width != AddressWidth::Auto {
+        return Err(ParseError::InvalidSyntax(format!("{}: Address width override without operand", span)));
     } else {
         None
     };
     
-    Ok(Instruction::new(opcode, operand))
+    Ok(Instruction::new(&opcode_str, operand).with_span(span))
+}
+
+/// Rockwell bit instructions that take the bit number as first operand
+const BIT_MNEMONICS: [&str; 4] = ["RMB", "SMB", "BBR", "BBS"];
+
+/// Parse the Rockwell syntax with the bit number as first operand,
+/// `rmb 3,zp` is `rmb3 zp` and `bbr 0,zp,target` is `bbr0 zp,target`
+fn parse_bit_instruction(name: &str, operand: Option<Pair<Rule>>, span: Span, scope: Scope) -> Result<Instruction, ParseError> {
+    let branch = name.starts_with("BB");
+    let operands = if branch {
+        "a bit number 0-7, a zero page address and a target"
+    } else {
+        "a bit number 0-7 and a zero page address"
+    };
+    let usage = || ParseError::InvalidSyntax(format!("{}: {} takes {}", span, name, operands));
+    
+    let mode_pair = operand.and_then(|pair| pair.into_inner().next()).ok_or_else(usage)?;
+    let expected = if branch { Rule::bit_triple } else { Rule::value_pair };
+    if mode_pair.as_rule() != expected {
+        return Err(usage());
+    }
+    
+    let values = mode_pair
+        .into_inner()
+        .map(|expr_pair| parse_expr(expr_pair.into_inner(), scope).map_err(|e| located(e, &span)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut values = values.into_iter();
+    let bit = match values.next() {
+        Some(Expr::Number(bit @ 0..=7)) => bit,
+        _ => return Err(usage()),
+    };
+    let address = values.next().ok_or_else(usage)?;
+    let operand = match values.next() {
+        Some(target) => Operand::Pair(address, target),
+        None => Operand::Address(address, AddressWidth::Auto),
+    };
+    
+    Ok(Instruction::new(&format!("{}{}", name, bit), Some(operand)).with_span(span))
 }
 
-fn parse_operand(pair: Pair<Rule>) -> Result<Operand, ParseError> {
-    let operand_str = pair.as_str();
-    Ok(Operand::parse(operand_str))
+fn parse_width_suffix(suffix: &str) -> Result<AddressWidth, ParseError> {
+    match suffix.to_lowercase().as_str() {
+        ".b" | ".z" => Ok(AddressWidth::ZeroPage),
+        ".w" | ".a" => Ok(AddressWidth::Absolute),
+        ".l" => Ok(AddressWidth::Long),
+        _ => Err(ParseError::InvalidSyntax(format!("Invalid address width: {}", suffix))),
+    }
+}
+
+fn parse_operand(pair: Pair<Rule>, width: AddressWidth, span: &Span, scope: Scope) -> Result<Operand, ParseError> {
+    let mode_pair = pair.into_inner().next().ok_or_else(|| ParseError::InvalidSyntax("Missing operand".to_string()))?;
+    let rule = mode_pair.as_rule();
+    let text = mode_pair.as_str();
+    
+    if rule == Rule::accumulator {
+        if width != AddressWidth::Auto {
+            return Err(ParseError::InvalidSyntax(format!(
+                "{}: Address width override not allowed for operand {}", span, text
+            )));
+        }
+        return Ok(Operand::Accumulator);
+    }
+    
+    let mut exprs = mode_pair.into_inner();
+    let expr_pair = exprs.next().ok_or_else(|| ParseError::InvalidSyntax("Missing operand value".to_string()))?;
+    let value = parse_expr(expr_pair.into_inner(), scope).map_err(|e| located(e, span))?;
+    
+    let operand = match rule {
+        Rule::value_pair => {
+            let second_pair = exprs.next().ok_or_else(|| ParseError::InvalidSyntax("Missing second operand value".to_string()))?;
+            let second = parse_expr(second_pair.into_inner(), scope).map_err(|e| located(e, span))?;
+            Operand::Pair(value, second)
+        }
+        Rule::stack_relative => Operand::StackRelative(value),
+        Rule::stack_indirect_indexed => Operand::StackRelativeIndirectIndexed(value),
+        Rule::indirect_long => Operand::IndirectLong(value),
+        Rule::indirect_long_indexed => Operand::IndirectLongIndexed(value),
+        Rule::immediate => Operand::Immediate(value),
+        Rule::indexed_indirect => Operand::IndexedIndirect(value),
+        Rule::indirect_indexed => Operand::IndirectIndexed(value),
+        Rule::indirect => Operand::Indirect(value),
+        Rule::indexed_x => return Ok(Operand::IndexedX(value, width)),
+        // There is no long form indexed with Y
+        Rule::indexed_y if width == AddressWidth::Long => {
+            return Err(ParseError::InvalidSyntax(format!(
+                "{}: Long address width not allowed for operand {}", span, text
+            )));
+        }
+        Rule::indexed_y => return Ok(Operand::IndexedY(value, width)),
+        Rule::address => return Ok(Operand::Address(value, width)),
+        _ => return Err(ParseError::InvalidSyntax(format!("Unexpected operand rule: {:?}", rule))),
+    };
+    
+    // Only plain and indexed addresses come in zero page and absolute forms
+    if width != AddressWidth::Auto {
+        return Err(ParseError::InvalidSyntax(format!(
+            "{}: Address width override not allowed for operand {}", span, text
+        )));
+    }
+    Ok(operand)
 }
 
-fn parse_directive(pair: Pair<Rule>) -> Result<Directive, ParseError> {
+fn parse_directive(pair: Pair<Rule>, scope: Scope) -> Result<Directive, ParseError> {
     let mut inner = pair.into_inner();
     
     let name_pair = inner.next().ok_or_else(|| ParseError::InvalidSyntax("Missing directive name".to_string()))?;
@@ -133,17 +356,83 @@ fn parse_directive(pair: Pair<Rule>) -> Result<Directive, ParseError> {
     
     let name = name_pair.as_str().trim_start_matches('.');
     
-    let value_pair = inner.next().ok_or_else(|| ParseError::InvalidSyntax("Missing directive value".to_string()))?;
-    if value_pair.as_rule() != Rule::directive_value {
-        return Err(ParseError::InvalidSyntax(format!("Expected directive value, got {:?}", value_pair.as_rule())));
-    }
-    
-    let value = value_pair.as_str();
+    // Some directives (e.g. .basicstub) take no arguments at all
+    let args = match inner.next() {
+        Some(args_pair) if args_pair.as_rule() == Rule::directive_args => {
+            args_pair.into_inner().map(|arg| parse_directive_arg(arg, scope)).collect::<Result<_, _>>()?
+        }
+        Some(args_pair) => {
+            return Err(ParseError::InvalidSyntax(format!("Expected directive arguments, got {:?}", args_pair.as_rule())));
+        }
+        None => Vec::new(),
+    };
     
-    Ok(Directive::new(name, value))
+    Ok(Directive::new(name, args))
+}
+
+fn parse_directive_arg(pair: Pair<Rule>, scope: Scope) -> Result<DirectiveArg, ParseError> {
+    match pair.as_rule() {
+        Rule::string_literal => Ok(DirectiveArg::String(parse_string(pair)?)),
+        Rule::bare_word => Ok(DirectiveArg::Expr(Expr::Symbol(pair.as_str().to_string()))),
+        Rule::expression => Ok(DirectiveArg::Expr(parse_expr(pair.into_inner(), scope)?)),
+        rule => Err(ParseError::InvalidSyntax(format!("Unexpected directive argument: {:?}", rule))),
+    }
+}
+
+/// Resolve the escape sequences of a string literal
+fn parse_string(pair: Pair<Rule>) -> Result<Text, ParseError> {
+    let chars = pair.into_inner().map(parse_text_char).collect::<Result<_, _>>()?;
+    Ok(Text { chars })
 }
 
-fn parse_constant(pair: Pair<Rule>) -> Result<(String, String), ParseError> {
+/// Parse a character literal such as `'a'` or `'\n'`
+fn parse_char(pair: Pair<Rule>) -> Result<char, ParseError> {
+    let literal = pair.as_str();
+    let chars = pair.into_inner().map(parse_text_char).collect::<Result<Vec<_>, _>>()?;
+    match chars.as_slice() {
+        [TextChar::Char(c)] => Ok(*c),
+        _ => Err(ParseError::InvalidSyntax(format!("Invalid character literal: {}", literal))),
+    }
+}
+
+/// A character of a string or character literal, backslash escape sequences
+/// stand for the escaped character and brace escapes (`{clr}`, `{$93}`) for
+/// control codes
+fn parse_text_char(pair: Pair<Rule>) -> Result<TextChar, ParseError> {
+    let text = pair.as_str();
+    let c = match pair.as_rule() {
+        Rule::escape => match &text[1..] {
+            "n" => '\n',
+            "r" => '\r',
+            "t" => '\t',
+            escaped => first_char(escaped)?,
+        },
+        Rule::control_code => return parse_control_code(&text[1..text.len() - 1]),
+        _ => first_char(text)?,
+    };
+    Ok(TextChar::Char(c))
+}
+
+/// The single character matched by a character rule
+fn first_char(text: &str) -> Result<char, ParseError> {
+    text.chars().next().ok_or_else(|| ParseError::InvalidSyntax("Empty character".to_string()))
+}
+
+/// Parse the name inside a brace escape, either a petcat name or a number
+fn parse_control_code(name: &str) -> Result<TextChar, ParseError> {
+    let name = name.trim();
+    if name.starts_with(|c: char| c == '$' || c == '%' || c.is_ascii_digit()) {
+        return match parse_number(name)? {
+            byte @ 0..=0xFF => Ok(TextChar::Byte(byte as u8)),
+            value => Err(ParseError::InvalidSyntax(format!("Control code out of range: {}", value))),
+        };
+    }
+    control_code(name)
+        .map(TextChar::Control)
+        .ok_or_else(|| ParseError::InvalidSyntax(format!("Unknown control code: {{{}}}", name)))
+}
+
+fn parse_constant(pair: Pair<Rule>, scope: Scope) -> Result<Constant, ParseError> {
     let mut inner = pair.into_inner();
     
     let name_pair = inner.next().ok_or_else(|| ParseError::InvalidSyntax("Missing constant name".to_string()))?;
@@ -152,13 +441,116 @@ fn parse_constant(pair: Pair<Rule>) -> Result<(String, String), ParseError> {
     }
     
     let name = name_pair.as_str();
+    check_symbol_name(name)?;
     
     let value_pair = inner.next().ok_or_else(|| ParseError::InvalidSyntax("Missing constant value".to_string()))?;
-    if value_pair.as_rule() != Rule::primary {
-        return Err(ParseError::InvalidSyntax(format!("Expected primary, got {:?}", value_pair.as_rule())));
+    if value_pair.as_rule() != Rule::expression {
+        return Err(ParseError::InvalidSyntax(format!("Expected expression, got {:?}", value_pair.as_rule())));
     }
     
-    let value = value_pair.as_str();
+    let value = parse_expr(value_pair.into_inner(), scope)?;
+    
+    Ok(Constant::new(name, value))
+}
+
+/// Parse a standalone expression, e.g. the text of an operand
+pub fn parse_expression(source: &str) -> Result<Expr, ParseError> {
+    let mut pairs = AssemblyParser::parse(Rule::expression_input, source)
+        .map_err(|e| ParseError::Pest(Box::new(e)))?;
+    
+    let expr_pair = pairs.next().ok_or_else(|| ParseError::InvalidSyntax("Missing expression".to_string()))?;
+    parse_expr(expr_pair.into_inner(), Scope::default())
+}
+
+/// Parse a standalone comma separated list of directive arguments
+pub fn parse_directive_args(source: &str) -> Result<Vec<DirectiveArg>, ParseError> {
+    let mut pairs = AssemblyParser::parse(Rule::directive_args_input, source)
+        .map_err(|e| ParseError::Pest(Box::new(e)))?;
     
-    Ok((name.to_string(), value.to_string()))
+    let args_pair = pairs.next().ok_or_else(|| ParseError::InvalidSyntax("Missing arguments".to_string()))?;
+    args_pair.into_inner().map(|arg| parse_directive_arg(arg, Scope::default())).collect()
+}
+
+/// Operator precedence for expressions, from lowest to highest
+static PRATT_PARSER: Lazy<PrattParser<Rule>> = Lazy::new(|| {
+    PrattParser::new()
+        .op(Op::infix(Rule::log_or, Assoc::Left))
+        .op(Op::infix(Rule::log_and, Assoc::Left))
+        .op(Op::infix(Rule::bit_or, Assoc::Left))
+        .op(Op::infix(Rule::bit_xor, Assoc::Left))
+        .op(Op::infix(Rule::bit_and, Assoc::Left))
+        .op(Op::infix(Rule::eq, Assoc::Left) | Op::infix(Rule::ne, Assoc::Left))
+        .op(Op::infix(Rule::lt, Assoc::Left) | Op::infix(Rule::le, Assoc::Left)
+            | Op::infix(Rule::gt, Assoc::Left) | Op::infix(Rule::ge, Assoc::Left))
+        .op(Op::infix(Rule::shl, Assoc::Left) | Op::infix(Rule::shr, Assoc::Left))
+        .op(Op::infix(Rule::add, Assoc::Left) | Op::infix(Rule::sub, Assoc::Left))
+        .op(Op::infix(Rule::mul, Assoc::Left) | Op::infix(Rule::div, Assoc::Left)
+            | Op::infix(Rule::rem, Assoc::Left))
+        .op(Op::prefix(Rule::neg) | Op::prefix(Rule::bit_not) | Op::prefix(Rule::log_not)
+            | Op::prefix(Rule::lo_byte) | Op::prefix(Rule::hi_byte) | Op::prefix(Rule::bank_byte))
+});
+
+/// Build an expression tree from the inner pairs of an `expression` rule,
+/// local and anonymous label references are resolved within `scope`
+fn parse_expr(pairs: Pairs<Rule>, scope: Scope) -> Result<Expr, ParseError> {
+    PRATT_PARSER
+        .map_primary(|primary| match primary.as_rule() {
+            Rule::number_literal => parse_number(primary.as_str()).map(Expr::Number),
+            Rule::char_literal => parse_char(primary).map(Expr::Char),
+            Rule::identifier | Rule::qualified_identifier => Ok(Expr::Symbol(primary.as_str().to_string())),
+            Rule::current_pc => Ok(Expr::CurrentPc),
+            Rule::local_identifier => qualify(primary.as_str(), scope).map(Expr::Symbol),
+            Rule::anonymous_ref => anonymous_reference(primary.as_str(), scope).map(Expr::Symbol),
+            Rule::expression => parse_expr(primary.into_inner(), scope),
+            rule => Err(ParseError::InvalidSyntax(format!("Unexpected rule in expression: {:?}", rule))),
+        })
+        .map_prefix(|op, rhs| {
+            let op = match op.as_rule() {
+                Rule::neg => UnaryOp::Negate,
+                Rule::bit_not => UnaryOp::BitNot,
+                Rule::log_not => UnaryOp::LogicalNot,
+                Rule::lo_byte => UnaryOp::LowByte,
+                Rule::hi_byte => UnaryOp::HighByte,
+                Rule::bank_byte => UnaryOp::BankByte,
+                rule => return Err(ParseError::InvalidSyntax(format!("Unexpected prefix operator: {:?}", rule))),
+            };
+            Ok(Expr::Unary(op, Box::new(rhs?)))
+        })
+        .map_infix(|lhs, op, rhs| {
+            let op = match op.as_rule() {
+                Rule::add => BinaryOp::Add,
+                Rule::sub => BinaryOp::Sub,
+                Rule::mul => BinaryOp::Mul,
+                Rule::div => BinaryOp::Div,
+                Rule::rem => BinaryOp::Mod,
+                Rule::bit_and => BinaryOp::BitAnd,
+                Rule::bit_or => BinaryOp::BitOr,
+                Rule::bit_xor => BinaryOp::BitXor,
+                Rule::shl => BinaryOp::Shl,
+                Rule::shr => BinaryOp::Shr,
+                Rule::eq => BinaryOp::Eq,
+                Rule::ne => BinaryOp::Ne,
+                Rule::lt => BinaryOp::Lt,
+                Rule::le => BinaryOp::Le,
+                Rule::gt => BinaryOp::Gt,
+                Rule::ge => BinaryOp::Ge,
+                Rule::log_and => BinaryOp::LogicalAnd,
+                Rule::log_or => BinaryOp::LogicalOr,
+                rule => return Err(ParseError::InvalidSyntax(format!("Unexpected infix operator: {:?}", rule))),
+            };
+            Ok(Expr::Binary(op, Box::new(lhs?), Box::new(rhs?)))
+        })
+        .parse(pairs)
+}
+
+/// Parse a numeric literal (`$ff`, `%1010` or `255`)
+fn parse_number(literal: &str) -> Result<i64, ParseError> {
+    let result = if let Some(hex) = literal.strip_prefix('$') {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(bin) = literal.strip_prefix('%') {
+        i64::from_str_radix(bin, 2)
+    } else {
+        literal.parse::<i64>()
+    };
+    result.map_err(|_| ParseError::InvalidSyntax(format!("Invalid number: {}", literal)))
 }
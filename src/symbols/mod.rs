@@ -0,0 +1,134 @@
+// Symbol table export for debuggers and other tools
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::ast::Span;
+
+/// Kind of a symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// Label defined by `name:`, its value is an address
+    Label,
+    
+    /// Constant defined by `NAME = value`
+    Constant,
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolKind::Label => write!(f, "label"),
+            SymbolKind::Constant => write!(f, "constant"),
+        }
+    }
+}
+
+/// A resolved symbol of an assembled program
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    /// Name of the symbol
+    pub name: String,
+    
+    /// Whether the symbol is a label or a constant
+    pub kind: SymbolKind,
+    
+    /// Address of a label or value of a constant
+    pub value: i64,
+    
+    /// Location of the definition in the source
+    pub span: Span,
+}
+
+/// Format of an exported symbol table
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymbolFormat {
+    /// VICE monitor label file (`al C:c000 .start`), load with `ll`
+    #[default]
+    Vice,
+    
+    /// JSON document with kind, value and definition location of every symbol
+    Json,
+}
+
+impl SymbolFormat {
+    /// Guess the symbol format from a file extension (`.json` or anything else)
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => SymbolFormat::Json,
+            _ => SymbolFormat::Vice,
+        }
+    }
+    
+    /// Render the symbol table
+    pub fn format(self, symbols: &[Symbol]) -> String {
+        match self {
+            SymbolFormat::Vice => format_vice(symbols),
+            SymbolFormat::Json => format_json(symbols),
+        }
+    }
+}
+
+impl FromStr for SymbolFormat {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "vice" => Ok(SymbolFormat::Vice),
+            "json" => Ok(SymbolFormat::Json),
+            _ => Err(format!("Unknown symbol format: {} (expected vice or json)", s)),
+        }
+    }
+}
+
+/// VICE only knows about addresses, so constants are left out
+fn format_vice(symbols: &[Symbol]) -> String {
+    symbols
+        .iter()
+        .filter(|symbol| symbol.kind == SymbolKind::Label)
+        .map(|symbol| format!("al C:{:04x} .{}\n", symbol.value, symbol.name))
+        .collect()
+}
+
+fn format_json(symbols: &[Symbol]) -> String {
+    let entries: Vec<String> = symbols
+        .iter()
+        .map(|symbol| {
+            format!(
+                "    {{\"name\": {}, \"kind\": \"{}\", \"value\": {}, \"file\": {}, \"line\": {}, \"column\": {}}}",
+                json_string(&symbol.name),
+                symbol.kind,
+                symbol.value,
+                json_string(&symbol.span.file),
+                symbol.span.line,
+                symbol.span.column,
+            )
+        })
+        .collect();
+    
+    if entries.is_empty() {
+        "{\n  \"symbols\": []\n}\n".to_string()
+    } else {
+        format!("{{\n  \"symbols\": [\n{}\n  ]\n}}\n", entries.join(",\n"))
+    }
+}
+
+/// Quote and escape a string for JSON
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,38 @@
+// Accumulator addressing tests for C64 assembly
+
+mod common;
+
+use common::{assemble, assemble_with};
+use rusm::assembler::Assembler;
+use rusm::isa::Cpu;
+use rusm::parse_source;
+
+#[test]
+fn operand_a_is_optional() {
+    assert_eq!(assemble("    asl\n    asl a\n    rol A\n"), [0x0A, 0x0A, 0x2A]);
+    assert_eq!(assemble("    lsr a\n    ror\n    ror a\n"), [0x4A, 0x6A, 0x6A]);
+}
+
+#[test]
+fn shifts_of_memory_are_not_accumulator_mode() {
+    assert_eq!(assemble("    asl $12\n    rol $1234\n"), [0x06, 0x12, 0x2E, 0x34, 0x12]);
+}
+
+#[test]
+fn increment_accumulator_on_the_65c02() {
+    let mut assembler = Assembler::new().cpu(Cpu::W65C02);
+    assert_eq!(assemble_with(&mut assembler, "    inc a\n    dec A\n    inc\n"), [0x1A, 0x3A, 0x1A]);
+}
+
+#[test]
+fn a_is_not_a_symbol_name() {
+    for source in ["a:\n    rts\n", "A:\n    rts\n", "a = 1\n", "A = 1\n"] {
+        let error = parse_source(source).unwrap_err().to_string();
+        assert!(error.contains("is the accumulator and cannot be used as a symbol name"), "{source:?}: {error}");
+    }
+}
+
+#[test]
+fn names_starting_with_a_are_symbols() {
+    assert_eq!(assemble("ab = $12\n    asl ab\n"), [0x06, 0x12]);
+}
@@ -0,0 +1,45 @@
+// Anonymous label tests for C64 assembly
+
+mod common;
+
+use common::{assemble, assemble_err};
+use rusm::parse_source;
+
+#[test]
+fn references_count_labels_in_their_direction() {
+    let source = ".org $1000\n-\n    dex\n-\n    bne -\n    bne --\n    beq +\n    beq ++\n+\n    nop\n+\n    rts\n";
+    assert_eq!(
+        assemble(source),
+        [0xCA, 0xD0, 0xFE, 0xD0, 0xFB, 0xF0, 0x02, 0xF0, 0x01, 0xEA, 0x60]
+    );
+}
+
+#[test]
+fn colon_label_is_reachable_from_both_directions() {
+    let source = ".org $1000\n    beq +\n:\n    nop\n    bne -\n";
+    assert_eq!(assemble(source), [0xF0, 0x00, 0xEA, 0xD0, 0xFD]);
+}
+
+#[test]
+fn missing_forward_label_is_reported_as_written() {
+    let error = assemble_err("    bne +\n");
+    assert!(error.contains("No anonymous label for '+' after this line"), "{error}");
+    
+    let error = assemble_err("    bne ++\n+\n    rts\n");
+    assert!(error.contains("No anonymous label for '++' after this line"), "{error}");
+    
+    let error = assemble_err("+\n    bne +++\n+\n    rts\n");
+    assert!(error.contains("No anonymous label for '+++' after this line"), "{error}");
+}
+
+#[test]
+fn missing_forward_label_in_constant_is_reported_as_written() {
+    let error = assemble_err("target = ++\n+\n    jmp target\n");
+    assert!(error.contains("No anonymous label for '++' after this line"), "{error}");
+}
+
+#[test]
+fn missing_backward_label_is_reported_as_written() {
+    let error = parse_source("-\n    bne --\n").unwrap_err().to_string();
+    assert!(error.contains("No anonymous label for '--' before this line"), "{error}");
+}
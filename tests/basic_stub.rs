@@ -0,0 +1,99 @@
+// BASIC stub tests for C64 assembly
+
+mod common;
+
+use common::assemble_with;
+use rusm::assembler::Assembler;
+
+/// Assemble `source` and return the load address, the SYS line number and
+/// the digits of the SYS address
+fn sys_line(source: &str) -> (usize, u16, String) {
+    let mut assembler = Assembler::new();
+    let binary = assemble_with(&mut assembler, source);
+    assert_eq!(binary[4], 0x9E, "no SYS token in {binary:02X?}");
+    let line = u16::from_le_bytes([binary[2], binary[3]]);
+    let end = binary[5..].iter().position(|&b| b == 0).expect("end of line") + 5;
+    let digits = String::from_utf8(binary[5..end].to_vec()).expect("digits");
+    (assembler.origin(), line, digits)
+}
+
+#[test]
+fn default_entry_follows_the_stub() {
+    let (origin, line, digits) = sys_line(".basicstub\n    lda #0\n    rts\n");
+    assert_eq!(origin, 0x0801);
+    assert_eq!(line, 10);
+    assert_eq!(digits, "2061");
+}
+
+#[test]
+fn default_entry_follows_a_later_origin() {
+    let source = ".basicstub\n.org $0900\nstart:\n    lda #0\n    rts\n";
+    let (origin, _, digits) = sys_line(source);
+    assert_eq!(origin, 0x0801);
+    assert_eq!(digits, "2304");
+    
+    let mut assembler = Assembler::new();
+    let binary = assemble_with(&mut assembler, source);
+    assert_eq!(binary[0x0900 - 0x0801..], [0xA9, 0x00, 0x60]);
+}
+
+#[test]
+fn default_entry_with_more_digits_follows_a_high_origin() {
+    let (_, _, digits) = sys_line(".basicstub\n.org $c000\n    rts\n");
+    assert_eq!(digits, "49152");
+}
+
+#[test]
+fn default_entry_skips_data_before_the_code() {
+    let (_, _, digits) = sys_line(".basicstub\n    .byte 1, 2, 3\n    rts\n");
+    assert_eq!(digits, "2064");
+}
+
+#[test]
+fn explicit_entry_and_line_are_used() {
+    let source = ".basicstub 2024, start\n.org $1000\nstart:\n    rts\n";
+    let (_, line, digits) = sys_line(source);
+    assert_eq!(line, 2024);
+    assert_eq!(digits, "4096");
+}
+
+/// Run `rusm assemble -b` on `source` and return the prg file
+fn assemble_with_stub_flag(name: &str, source: &str) -> Vec<u8> {
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("rusm-stub-{name}-{}.asm", std::process::id()));
+    let output = input.with_extension("prg");
+    std::fs::write(&input, source).unwrap();
+    
+    let run = std::process::Command::new(env!("CARGO_BIN_EXE_rusm"))
+        .arg("assemble")
+        .arg(&input)
+        .arg("-o")
+        .arg(&output)
+        .arg("-b")
+        .output()
+        .unwrap();
+    let binary = std::fs::read(&output);
+    std::fs::remove_file(&input).unwrap();
+    std::fs::remove_file(&output).ok();
+    
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    binary.unwrap()
+}
+
+#[test]
+fn stub_flag_is_placed_at_a_leading_origin() {
+    let binary = assemble_with_stub_flag("pc", "* = $0801\n    lda #0\n    rts\n");
+    assert_eq!(binary[..2], [0x01, 0x08]);
+    assert_eq!(binary[6], 0x9E);
+    assert_eq!(binary[7..11], *b"2061");
+    assert_eq!(binary[binary.len() - 3..], [0xA9, 0x00, 0x60]);
+}
+
+#[test]
+fn stub_flag_follows_constants_and_a_high_origin() {
+    let source = "BORDER = $d020\n.org $c000\n    sta BORDER\n    rts\n";
+    let binary = assemble_with_stub_flag("high", source);
+    assert_eq!(binary[..2], [0x00, 0xC0]);
+    assert_eq!(binary[7..12], *b"49165");
+    assert_eq!(binary.len(), 2 + 13 + 4);
+}
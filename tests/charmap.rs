@@ -0,0 +1,40 @@
+// Character map tests for C64 assembly
+
+mod common;
+
+use common::{assemble, assemble_err};
+use std::fs;
+
+#[test]
+fn charmap_remaps_characters_and_ranges() {
+    let source = ".charmap 'a', 'c', $41\n.charmap '@', 0\n    .text \"abc@d\"\n";
+    assert_eq!(assemble(source), [0x41, 0x42, 0x43, 0x00, 0x64]);
+}
+
+#[test]
+fn pushed_charmap_is_restored() {
+    let source = ".pushcharmap \"font\"\n.charmap 'a', 1\n    .text \"a\"\n.popcharmap\n    .text \"a\"\n";
+    assert_eq!(assemble(source), [0x01, 0x61]);
+}
+
+#[test]
+fn charmap_file_is_applied_on_every_pass() {
+    let path = std::env::temp_dir().join(format!("rusm-charmap-{}.map", std::process::id()));
+    fs::write(&path, "; lower case to screen codes\n'a', 'z', 1\n").unwrap();
+    
+    // The forward reference makes the layout take several passes, each of
+    // which applies the file again
+    let source = format!(
+        ".charmapload \"{}\"\n    lda later\n    .text \"az\"\nlater:\n    rts\n",
+        path.display()
+    );
+    let binary = assemble(&source);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(binary, [0xAD, 0x05, 0x10, 0x01, 0x1A, 0x60]);
+}
+
+#[test]
+fn missing_charmap_file_is_an_error() {
+    let error = assemble_err(".charmapload \"/nonexistent/rusm.map\"\n");
+    assert!(error.contains("Cannot read character map file /nonexistent/rusm.map"), "{error}");
+}
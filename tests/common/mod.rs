@@ -0,0 +1,38 @@
+// Shared helpers for the integration tests
+#![allow(dead_code)]
+
+use rusm::assembler::Assembler;
+use rusm::parse_source;
+
+/// Assemble `source` with the default settings, panicking on errors
+pub fn assemble(source: &str) -> Vec<u8> {
+    assemble_with(&mut Assembler::new(), source)
+}
+
+/// Assemble `source` with a configured assembler, panicking on errors
+pub fn assemble_with(assembler: &mut Assembler, source: &str) -> Vec<u8> {
+    let ast = parse_source(source).unwrap_or_else(|e| panic!("{source:?} does not parse: {e}"));
+    assembler.assemble(&ast).unwrap_or_else(|e| panic!("{source:?} does not assemble: {e}"))
+}
+
+/// Assemble `source` with the default settings and return the error message
+pub fn assemble_err(source: &str) -> String {
+    assemble_err_with(&mut Assembler::new(), source)
+}
+
+/// Assemble `source` with a configured assembler and return the error message
+pub fn assemble_err_with(assembler: &mut Assembler, source: &str) -> String {
+    let ast = parse_source(source).unwrap_or_else(|e| panic!("{source:?} does not parse: {e}"));
+    match assembler.assemble(&ast) {
+        Ok(binary) => panic!("{source:?} assembled to {binary:02X?}"),
+        Err(e) => e.to_string(),
+    }
+}
+
+/// Value of a label or constant after assembly
+pub fn symbol(assembler: &Assembler, name: &str) -> i64 {
+    assembler.symbols().iter()
+        .find(|s| s.name == name)
+        .unwrap_or_else(|| panic!("no symbol {name}"))
+        .value
+}
@@ -0,0 +1,170 @@
+// Processor selection tests for C64 assembly
+
+mod common;
+
+use common::{assemble, assemble_err, assemble_err_with, assemble_with};
+use rusm::assembler::Assembler;
+use rusm::isa::Cpu;
+use rusm::parse_source;
+
+#[test]
+fn default_cpu_is_the_6510_with_undocumented_opcodes() {
+    assert_eq!(Cpu::default(), Cpu::Mos6510);
+    assert_eq!(assemble("    lax $12\n"), [0xA7, 0x12]);
+    let error = assemble_err("    stz $12\n");
+    assert!(error.contains("STZ is not available on the 6510"), "{error}");
+}
+
+#[test]
+fn cpu_names_parse_case_insensitively() {
+    let names = [
+        ("6502", Cpu::Mos6502), ("6510", Cpu::Mos6510), ("65C02", Cpu::Cmos65C02),
+        ("r65c02", Cpu::R65C02), ("W65C02S", Cpu::W65C02), ("65c816", Cpu::W65816),
+    ];
+    for (name, cpu) in names {
+        assert_eq!(name.parse::<Cpu>(), Ok(cpu), "{name}");
+    }
+    assert!("z80".parse::<Cpu>().is_err());
+}
+
+#[test]
+fn cpu_directive_switches_the_instruction_set() {
+    let source = ".cpu 65c02\n    stz $12\n    bra *\n.cpu 6510\n    lax $12\n";
+    assert_eq!(assemble(source), [0x64, 0x12, 0x80, 0xFE, 0xA7, 0x12]);
+    
+    let error = assemble_err(".cpu 65c02\n    stz $12\n.cpu 6510\n    stz $12\n");
+    assert!(error.contains(":4:5: STZ is not available on the 6510"), "{error}");
+}
+
+#[test]
+fn cpu_directive_accepts_quoted_names() {
+    assert_eq!(assemble(".cpu \"w65c02\"\n    wai\n"), [0xCB]);
+}
+
+#[test]
+fn unknown_cpu_is_an_error() {
+    let error = assemble_err(".cpu z80\n");
+    assert!(error.contains("z80"), "{error}");
+}
+
+#[test]
+fn builder_cpu_is_restored_on_every_assembly() {
+    let mut assembler = Assembler::new().cpu(Cpu::Cmos65C02);
+    assert_eq!(assemble_with(&mut assembler, ".cpu 6510\n    nop\n"), [0xEA]);
+    assert_eq!(assemble_with(&mut assembler, "    stz $1234\n"), [0x9C, 0x34, 0x12]);
+}
+
+#[test]
+fn cmos_addressing_modes_and_timings() {
+    let mut assembler = Assembler::new().cpu(Cpu::Cmos65C02);
+    let source = "    lda ($12)\n    jmp ($1234,x)\n    bit #$12\n    inc\n    jmp ($1234)\n";
+    assert_eq!(
+        assemble_with(&mut assembler, source),
+        [0xB2, 0x12, 0x7C, 0x34, 0x12, 0x89, 0x12, 0x1A, 0x6C, 0x34, 0x12]
+    );
+    let cycles: Vec<_> = assembler.listing().iter().map(|entry| entry.cycles).collect();
+    assert_eq!(cycles, [Some(5), Some(6), Some(2), Some(2), Some(6)]);
+    
+    let mut assembler = Assembler::new();
+    assemble_with(&mut assembler, "    jmp ($1234)\n");
+    assert_eq!(assembler.listing()[0].cycles, Some(5));
+}
+
+#[test]
+fn unsupported_mnemonics_are_rejected_per_cpu() {
+    let rejected = [
+        (Cpu::Mos6502, "bra *", "BRA is not available on the 6502"),
+        (Cpu::Mos6510, "phx", "PHX is not available on the 6510"),
+        (Cpu::Cmos65C02, "lax $12", "LAX is not available on the 65c02"),
+        (Cpu::Cmos65C02, "rmb0 $12", "RMB0 is not available on the 65c02"),
+        (Cpu::R65C02, "wai", "WAI is not available on the r65c02"),
+        (Cpu::W65C02, "xba", "XBA is not available on the w65c02"),
+        (Cpu::W65816, "smb0 $12", "SMB0 is not available on the 65816"),
+    ];
+    for (cpu, line, message) in rejected {
+        let error = assemble_err_with(&mut Assembler::new().cpu(cpu), &format!("    {line}\n"));
+        assert!(error.contains(message), "{cpu}: {error}");
+    }
+}
+
+#[test]
+fn rockwell_bit_instructions_encode_the_bit_number() {
+    let mut assembler = Assembler::new().cpu(Cpu::R65C02);
+    let source = "    rmb0 $12\n    rmb7 $12\n    smb0 $12\n    smb7 $12\n";
+    assert_eq!(assemble_with(&mut assembler, source), [0x07, 0x12, 0x77, 0x12, 0x87, 0x12, 0xF7, 0x12]);
+}
+
+#[test]
+fn bit_branches_encode_zero_page_then_offset() {
+    let mut assembler = Assembler::new().cpu(Cpu::R65C02);
+    let source = ".org $1000\n-\n    bbr0 $12, -\n    bbs7 $fe, +\n    nop\n+\n    rts\n";
+    assert_eq!(
+        assemble_with(&mut assembler, source),
+        [0x0F, 0x12, 0xFD, 0xFF, 0xFE, 0x01, 0xEA, 0x60]
+    );
+    let cycles: Vec<_> = assembler.listing().iter().filter_map(|entry| entry.cycles).collect();
+    assert_eq!(cycles, [5, 5, 2, 6]);
+}
+
+#[test]
+fn bit_number_can_be_the_first_operand() {
+    let mut assembler = Assembler::new().cpu(Cpu::R65C02);
+    let source = "    rmb 3,$12\n    smb 7, $12\n    RMB 0,$fe\n";
+    assert_eq!(assemble_with(&mut assembler, source), [0x37, 0x12, 0xF7, 0x12, 0x07, 0xFE]);
+    
+    let source = ".org $1000\n-\n    bbr 0,$12,-\n    bbs 7, $fe, +\n    nop\n+\n    rts\n";
+    assert_eq!(
+        assemble_with(&mut assembler, source),
+        [0x0F, 0x12, 0xFD, 0xFF, 0xFE, 0x01, 0xEA, 0x60]
+    );
+}
+
+#[test]
+fn bit_number_operand_is_checked() {
+    for line in ["rmb 8,$12", "smb $12", "rmb bit,$12", "bbr 0,$12", "rmb 1,$12,*"] {
+        let error = parse_source(&format!("    {line}\n")).unwrap_err().to_string();
+        assert!(error.contains("takes a bit number 0-7"), "{line}: {error}");
+    }
+    
+    let error = assemble_err_with(&mut Assembler::new().cpu(Cpu::Cmos65C02), "    rmb 3,$12\n");
+    assert!(error.contains("RMB3 is not available on the 65c02"), "{error}");
+}
+
+#[test]
+fn bit_branch_operands_are_checked() {
+    let mut assembler = Assembler::new().cpu(Cpu::W65C02);
+    let error = assemble_err_with(&mut assembler, "    bbr3 $1234, *\n");
+    assert!(error.contains("Zero page address"), "{error}");
+    
+    let error = assemble_err_with(&mut assembler, ".org $1000\n    bbs1 $12, $1100\n");
+    assert!(error.contains("out of range"), "{error}");
+}
+
+#[test]
+fn cpu_flag_selects_the_initial_cpu() {
+    let dir = std::env::temp_dir();
+    let source = dir.join(format!("rusm-cpu-{}.asm", std::process::id()));
+    let output = source.with_extension("bin");
+    std::fs::write(&source, "    stz $12\n").unwrap();
+    
+    let run = |cpu: &str| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_rusm"))
+            .arg("assemble")
+            .arg(&source)
+            .arg("-o")
+            .arg(&output)
+            .args(["-f", "raw", "--cpu", cpu])
+            .output()
+            .unwrap()
+    };
+    let rejected = run("6510");
+    let accepted = run("65c02");
+    let binary = std::fs::read(&output);
+    std::fs::remove_file(&source).unwrap();
+    std::fs::remove_file(&output).ok();
+    
+    assert!(!rejected.status.success());
+    assert!(String::from_utf8_lossy(&rejected.stderr).contains("STZ is not available on the 6510"));
+    assert!(accepted.status.success(), "{}", String::from_utf8_lossy(&accepted.stderr));
+    assert_eq!(binary.unwrap(), [0x64, 0x12]);
+}
@@ -0,0 +1,74 @@
+// Expression evaluation tests for C64 assembly
+
+mod common;
+
+use common::{assemble, assemble_err};
+
+/// Assemble `.byte <expr>` and return the single emitted byte
+fn byte(expr: &str) -> u8 {
+    let binary = assemble(&format!(".byte {expr}\n"));
+    assert_eq!(binary.len(), 1, "{expr}");
+    binary[0]
+}
+
+#[test]
+fn multiplication_binds_tighter_than_addition() {
+    assert_eq!(byte("1+2*3"), 7);
+    assert_eq!(byte("2*3+1"), 7);
+    assert_eq!(byte("(1+2)*3"), 9);
+}
+
+#[test]
+fn binary_operators_are_left_associative() {
+    assert_eq!(byte("10-4-3"), 3);
+    assert_eq!(byte("64/4/2"), 8);
+    assert_eq!(byte("1<<2<<1"), 8);
+}
+
+#[test]
+fn shifts_bind_looser_than_addition() {
+    assert_eq!(byte("1<<1+1"), 4);
+    assert_eq!(byte("$80>>2+1"), 0x10);
+}
+
+#[test]
+fn bitwise_operators_bind_looser_than_comparisons() {
+    assert_eq!(byte("1|2&3"), 3);
+    assert_eq!(byte("1|2^3"), 1);
+    assert_eq!(byte("1==1&1"), 1);
+    assert_eq!(byte("2&3==3"), 0);
+}
+
+#[test]
+fn unary_minus_binds_tighter_than_multiplication() {
+    assert_eq!(byte("-2*3+10"), 4);
+    assert_eq!(byte("10+-2*3"), 4);
+    assert_eq!(byte("-(2*3)+10"), 4);
+}
+
+#[test]
+fn byte_selectors_apply_to_the_following_term() {
+    let source = ".org $12fe\nlabel:\n    .byte <label+1, >label+1, <(label+1), >(label+1)\n";
+    assert_eq!(assemble(source), [0xFF, 0x13, 0xFF, 0x12]);
+    let source = ".org $c0ff\nlabel:\n    .byte <(label+1), >(label+1)\n";
+    assert_eq!(assemble(source), [0x00, 0xC1]);
+    let error = assemble_err(".org $c0ff\nlabel:\n    .byte <label+1\n");
+    assert!(error.contains("Byte value out of range: 256"), "{error}");
+}
+
+#[test]
+fn shift_by_negative_amount_is_an_error() {
+    let error = assemble_err(".byte 1<<-1\n");
+    assert!(error.contains("Shift amount out of range"), "{error}");
+    let error = assemble_err(".byte 1>>-1\n");
+    assert!(error.contains("Shift amount out of range"), "{error}");
+}
+
+#[test]
+fn shift_by_word_size_or_more_is_an_error() {
+    let error = assemble_err(".byte 1<<64\n");
+    assert!(error.contains("Shift amount out of range"), "{error}");
+    let error = assemble_err(".byte 1>>100\n");
+    assert!(error.contains("Shift amount out of range"), "{error}");
+    assert_eq!(byte("1<<63>>63"), 0xFF);
+}
@@ -0,0 +1,216 @@
+// Instruction set tests for C64 assembly
+
+mod common;
+
+use common::{assemble_err_with, assemble_with};
+use rusm::assembler::Assembler;
+use rusm::ast::{AddressingMode, Opcode};
+use std::sync::Arc;
+
+use rusm::isa::{Cpu, InstructionSet, OpcodeEntry, OpcodeTable, TableInstructionSet};
+
+#[test]
+fn every_table_entry_decodes_back_to_its_byte() {
+    for cpu in Cpu::ALL {
+        let isa = cpu.instruction_set();
+        for opcode in isa.instructions() {
+            for mode in isa.addressing_modes(opcode) {
+                let entry = isa.entry(opcode, mode).unwrap();
+                let (decoded, decoded_mode, decoded_entry) = isa.decode(entry.byte)
+                    .unwrap_or_else(|| panic!("{cpu}: ${:02X} does not decode", entry.byte));
+                assert_eq!(decoded_entry.byte, entry.byte, "{cpu}: {opcode:?} {mode:?}");
+                assert_eq!(decoded_entry.size, entry.size, "{cpu}: {opcode:?} {mode:?}");
+                if (decoded, decoded_mode) != (opcode, mode) {
+                    // Shared bytes need exactly one preferred entry
+                    assert!(decoded_entry.preferred && !entry.preferred,
+                        "{cpu}: ${:02X} is shared by {opcode:?} {mode:?} and {decoded:?} {decoded_mode:?}", entry.byte);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn every_decoded_byte_has_a_table_entry() {
+    for cpu in Cpu::ALL {
+        let isa = cpu.instruction_set();
+        for byte in 0..=0xFF {
+            if let Some((opcode, mode, entry)) = isa.decode(byte) {
+                // Decode-only bytes share the size of the instruction they duplicate
+                assert_eq!(entry.byte, byte, "{cpu}");
+                assert_eq!(isa.entry(opcode, mode).map(|entry| entry.size), Some(entry.size), "{cpu}: ${byte:02X}");
+            }
+        }
+    }
+    
+    // Every byte does something on NMOS parts, documented or not
+    for cpu in [Cpu::Mos6502, Cpu::Mos6510] {
+        let isa = cpu.instruction_set();
+        let missing: Vec<_> = (0..=0xFF).filter(|&byte| isa.decode(byte).is_none()).collect();
+        assert!(missing.is_empty(), "{cpu} does not decode {missing:02X?}");
+    }
+}
+
+#[test]
+fn undocumented_duplicates_decode_but_are_not_emitted() {
+    let nmos = Cpu::Mos6510.instruction_set();
+    let decode = |byte| nmos.decode(byte).map(|d| (d.0, d.1, d.2.size));
+    assert_eq!(decode(0x72), Some((Opcode::JAM, AddressingMode::Implied, 1)));
+    assert_eq!(decode(0xFA), Some((Opcode::NOP, AddressingMode::Implied, 1)));
+    assert_eq!(decode(0x89), Some((Opcode::NOP, AddressingMode::Immediate, 2)));
+    assert_eq!(decode(0x64), Some((Opcode::NOP, AddressingMode::ZeroPage, 2)));
+    assert_eq!(decode(0xF4), Some((Opcode::NOP, AddressingMode::ZeroPageX, 2)));
+    assert_eq!(decode(0xDC), Some((Opcode::NOP, AddressingMode::AbsoluteX, 3)));
+    
+    let mut assembler = Assembler::new().cpu(Cpu::Mos6510);
+    assert_eq!(assemble_with(&mut assembler, "    brk\n    jam\n"), [0x00, 0x02]);
+    assert_eq!(assemble_with(&mut assembler, "    nop\n    nop #1\n    nop $1234,x\n"), [0xEA, 0x80, 0x01, 0x1C, 0x34, 0x12]);
+}
+
+#[test]
+fn shared_bytes_decode_to_the_preferred_mnemonic() {
+    let w65816 = Cpu::W65816.instruction_set();
+    assert_eq!(w65816.decode(0x5C).map(|d| (d.0, d.1)), Some((Opcode::JML, AddressingMode::AbsoluteLong)));
+    assert_eq!(w65816.decode(0xDC).map(|d| (d.0, d.1)), Some((Opcode::JML, AddressingMode::AbsoluteIndirectLong)));
+    assert_eq!(w65816.decode(0x22).map(|d| (d.0, d.1)), Some((Opcode::JSL, AddressingMode::AbsoluteLong)));
+    let nmos = Cpu::Mos6510.instruction_set();
+    assert_eq!(nmos.decode(0xAB).map(|d| (d.0, d.1)), Some((Opcode::LXA, AddressingMode::Immediate)));
+}
+
+#[test]
+fn decoding_depends_on_the_cpu() {
+    let decode = |cpu: Cpu, byte| cpu.instruction_set().decode(byte).map(|d| (d.0, d.1, d.2.cycles));
+    assert_eq!(decode(Cpu::Mos6510, 0x07), Some((Opcode::SLO, AddressingMode::ZeroPage, 5)));
+    assert_eq!(decode(Cpu::Cmos65C02, 0x07), None);
+    assert_eq!(decode(Cpu::R65C02, 0x07), Some((Opcode::RMB0, AddressingMode::ZeroPage, 5)));
+    assert_eq!(decode(Cpu::W65816, 0x07), Some((Opcode::ORA, AddressingMode::ZeroPageIndirectLong, 6)));
+    assert_eq!(decode(Cpu::Mos6510, 0x6C), Some((Opcode::JMP, AddressingMode::Indirect, 5)));
+    assert_eq!(decode(Cpu::Cmos65C02, 0x6C), Some((Opcode::JMP, AddressingMode::Indirect, 6)));
+}
+
+#[test]
+fn mnemonics_resolve_for_supporting_cpus_only() {
+    let nmos = Cpu::Mos6510.instruction_set();
+    let cmos = Cpu::Cmos65C02.instruction_set();
+    assert_eq!(nmos.mnemonic("lda"), Some(Opcode::LDA));
+    assert_eq!(nmos.mnemonic("ISB"), Some(Opcode::ISC));
+    assert_eq!(nmos.mnemonic("stz"), None);
+    assert_eq!(cmos.mnemonic("stz"), Some(Opcode::STZ));
+    assert_eq!(cmos.mnemonic("isb"), None);
+    assert_eq!(cmos.mnemonic("nonsense"), None);
+}
+
+#[test]
+fn cpu_names_its_instruction_set() {
+    for cpu in Cpu::ALL {
+        assert_eq!(cpu.instruction_set().name(), cpu.to_string());
+    }
+    assert!(Cpu::W65816.instruction_set().long_addressing());
+    assert!(!Cpu::W65C02.instruction_set().long_addressing());
+}
+
+/// A processor that only loads and stores the accumulator
+fn tiny_instruction_set() -> Arc<dyn InstructionSet> {
+    let mut table = OpcodeTable::new();
+    table.insert((Opcode::LDA, AddressingMode::Immediate), OpcodeEntry::new(0xA9, 2, 2));
+    table.insert((Opcode::STA, AddressingMode::Absolute), OpcodeEntry::new(0x8D, 3, 4));
+    table.insert((Opcode::RTS, AddressingMode::Implied), OpcodeEntry::new(0x60, 1, 6));
+    Arc::new(TableInstructionSet::new("tiny", table))
+}
+
+#[test]
+fn assembler_accepts_an_instruction_set_without_a_cpu() {
+    let mut assembler = Assembler::new().instruction_set(tiny_instruction_set());
+    let source = "    lda #1\n    sta $d020\n    rts\n";
+    assert_eq!(assemble_with(&mut assembler, source), [0xA9, 0x01, 0x8D, 0x20, 0xD0, 0x60]);
+    
+    let error = assemble_err_with(&mut assembler, "    ldx #1\n");
+    assert!(error.contains("LDX is not available on the tiny"), "{error}");
+    
+    // A table without zero page modes keeps zero page operands absolute
+    assert_eq!(assemble_with(&mut assembler, "    sta $fb\n"), [0x8D, 0xFB, 0x00]);
+    
+    // .cpu still switches to the built-in tables, until the next assembly
+    assert_eq!(assemble_with(&mut assembler, ".cpu 6510\n    ldx #1\n"), [0xA2, 0x01]);
+    assert!(assemble_err_with(&mut assembler, "    ldx #1\n").contains("tiny"));
+}
+
+/// A processor with instructions of its own next to some of the 6502's
+struct BlinkingCpu;
+
+impl InstructionSet for BlinkingCpu {
+    fn name(&self) -> &str {
+        "blinker"
+    }
+    
+    fn entry(&self, opcode: Opcode, mode: AddressingMode) -> Option<OpcodeEntry> {
+        match (opcode, mode) {
+            (Opcode::Custom("BLINK"), AddressingMode::Immediate) => Some(OpcodeEntry::new(0x42, 2, 3)),
+            (Opcode::Custom("WAIT"), AddressingMode::Implied) => Some(OpcodeEntry::new(0x43, 1, 2)),
+            (Opcode::RTS, AddressingMode::Implied) => Some(OpcodeEntry::new(0x60, 1, 6)),
+            _ => None,
+        }
+    }
+    
+    fn instructions(&self) -> Vec<Opcode> {
+        vec![Opcode::Custom("BLINK"), Opcode::Custom("WAIT"), Opcode::RTS]
+    }
+    
+    fn addressing_modes(&self, opcode: Opcode) -> Vec<AddressingMode> {
+        match opcode {
+            Opcode::Custom("BLINK") => vec![AddressingMode::Immediate],
+            Opcode::Custom("WAIT") | Opcode::RTS => vec![AddressingMode::Implied],
+            _ => Vec::new(),
+        }
+    }
+    
+    fn decode(&self, byte: u8) -> Option<(Opcode, AddressingMode, OpcodeEntry)> {
+        for opcode in self.instructions() {
+            for mode in self.addressing_modes(opcode) {
+                let entry = self.entry(opcode, mode)?;
+                if entry.byte == byte {
+                    return Some((opcode, mode, entry));
+                }
+            }
+        }
+        None
+    }
+    
+    fn mnemonic(&self, name: &str) -> Option<Opcode> {
+        match name.to_uppercase().as_str() {
+            "FLASH" => Some(Opcode::Custom("BLINK")),
+            name => self.instructions().into_iter().find(|opcode| opcode.to_string() == name),
+        }
+    }
+}
+
+#[test]
+fn instruction_set_adds_its_own_mnemonics() {
+    let mut assembler = Assembler::new().instruction_set(Arc::new(BlinkingCpu));
+    let source = "    blink #3\n    flash #1\n    wait\n    rts\n";
+    assert_eq!(assemble_with(&mut assembler, source), [0x42, 0x03, 0x42, 0x01, 0x43, 0x60]);
+    
+    let error = assemble_err_with(&mut assembler, "    wait $12\n");
+    assert!(error.contains("Invalid addressing mode"), "{error}");
+    
+    let error = assemble_err_with(&mut assembler, "    lda #1\n");
+    assert!(error.contains("LDA is not available on the blinker"), "{error}");
+    
+    let error = assemble_err_with(&mut assembler, "    blonk #1\n");
+    assert!(error.contains("Unknown opcode: BLONK"), "{error}");
+    
+    // Built-in processors do not know the new mnemonics
+    let error = assemble_err_with(&mut Assembler::new(), "    blink #3\n");
+    assert!(error.contains("Unknown opcode: BLINK"), "{error}");
+}
+
+#[test]
+fn absolute_aliases_depend_on_the_instruction_set() {
+    let nmos = Cpu::Mos6510.instruction_set();
+    assert!(nmos.absolute_alias("top"));
+    assert!(!nmos.absolute_alias("nop"));
+    assert!(!BlinkingCpu.absolute_alias("top"));
+    
+    let mut assembler = Assembler::new().cpu(Cpu::Mos6510);
+    assert_eq!(assemble_with(&mut assembler, "    top $12\n    dop $12\n"), [0x0C, 0x12, 0x00, 0x04, 0x12]);
+}
@@ -0,0 +1,84 @@
+// Layout pass tests for C64 assembly
+
+mod common;
+
+use common::{assemble, assemble_err, assemble_with, symbol};
+use rusm::assembler::Assembler;
+
+#[test]
+fn backward_reference_to_zero_page_uses_zero_page_mode() {
+    let source = ".org $80\ndata:\n    .byte $42\n    lda data\n    rts\n";
+    assert_eq!(assemble(source), [0x42, 0xA5, 0x80, 0x60]);
+}
+
+#[test]
+fn forward_reference_to_zero_page_shrinks_to_zero_page_mode() {
+    let source = ".org $80\nstart:\n    lda data\n    rts\ndata:\n    .byte $42\n";
+    let mut assembler = Assembler::new();
+    assert_eq!(assemble_with(&mut assembler, source), [0xA5, 0x83, 0x60, 0x42]);
+    assert_eq!(symbol(&assembler, "start"), 0x80);
+    assert_eq!(symbol(&assembler, "data"), 0x83);
+}
+
+#[test]
+fn shrinking_instruction_moves_later_labels_into_zero_page() {
+    // The first pass puts `first` at $FF and `second` at $100, only once
+    // the load of `first` has shrunk does `second` fit in zero page as well
+    let source = ".org $f9\n    lda first\n    lda second\nfirst:\n    .byte 1\nsecond:\n    .byte 2\n";
+    let mut assembler = Assembler::new();
+    assert_eq!(assemble_with(&mut assembler, source), [0xA5, 0xFD, 0xA5, 0xFE, 0x01, 0x02]);
+    assert_eq!(symbol(&assembler, "first"), 0xFD);
+    assert_eq!(symbol(&assembler, "second"), 0xFE);
+}
+
+#[test]
+fn forward_reference_outside_zero_page_stays_absolute() {
+    let source = ".org $1000\n    lda data\n    jmp done\ndata:\n    .byte $42\ndone:\n    rts\n";
+    let mut assembler = Assembler::new();
+    assert_eq!(
+        assemble_with(&mut assembler, source),
+        [0xAD, 0x06, 0x10, 0x4C, 0x07, 0x10, 0x42, 0x60]
+    );
+    assert_eq!(symbol(&assembler, "data"), 0x1006);
+    assert_eq!(symbol(&assembler, "done"), 0x1007);
+}
+
+#[test]
+fn forward_reference_through_constant_shrinks_to_zero_page_mode() {
+    let source = ".org $1000\n    sta pointer\n    rts\npointer = base + 2\nbase = $fb\n";
+    assert_eq!(assemble(source), [0x85, 0xFD, 0x60]);
+}
+
+#[test]
+fn forced_absolute_width_is_kept_for_zero_page_addresses() {
+    let source = ".org $80\n    lda.a data\ndata:\n    .byte $42\n";
+    assert_eq!(assemble(source), [0xAD, 0x83, 0x00, 0x42]);
+}
+
+#[test]
+fn unknown_symbol_is_an_error_in_the_final_pass() {
+    let error = assemble_err(".org $1000\n    lda missing\n");
+    assert!(error.contains("Unknown label: missing"), "{error}");
+}
+
+#[test]
+fn oscillating_layout_reports_non_convergence() {
+    // As a zero page load `target` is $1002 and the operand $100, as an
+    // absolute load `target` is $1003 and the operand $FF: neither settles
+    let error = assemble_err(".org $1000\n    lda $1102 - target\ntarget:\n    rts\n");
+    assert!(error.contains("did not converge after 16 passes"), "{error}");
+}
+
+#[test]
+fn reused_assembler_starts_at_the_default_origin() {
+    let mut assembler = Assembler::new();
+    assemble_with(&mut assembler, ".org $c000\n    rts\n");
+    assert_eq!(assembler.origin(), 0xC000);
+    assert_eq!(assemble_with(&mut assembler, "l:\n    jmp l\n"), [0x4C, 0x00, 0x10]);
+    assert_eq!(assembler.origin(), 0x1000);
+    
+    assemble_with(&mut assembler, ".basicstub\n    rts\n");
+    assert_eq!(assembler.origin(), 0x0801);
+    assert_eq!(assemble_with(&mut assembler, "    rts\n"), [0x60]);
+    assert_eq!(assembler.origin(), 0x1000);
+}
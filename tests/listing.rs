@@ -0,0 +1,76 @@
+// Listing output tests for C64 assembly
+
+use rusm::assembler::Assembler;
+use rusm::ast::Span;
+use rusm::listing::{format_listing, ListingEntry};
+use rusm::parse_source_named;
+
+/// Assemble `source` as `test.asm` and return the rows of its listing
+fn listing(source: &str) -> Vec<String> {
+    let ast = parse_source_named(source, "test.asm").unwrap();
+    let mut assembler = Assembler::new();
+    assembler.assemble(&ast).unwrap();
+    format_listing(source, "test.asm", assembler.listing()).lines().map(String::from).collect()
+}
+
+#[test]
+fn label_only_lines_show_their_address() {
+    let expected = [
+        "    1  1000                     .org $1000",
+        "    2  1000                     start:",
+        "    3  1000  60              6      rts",
+    ];
+    assert_eq!(listing(".org $1000\nstart:\n    rts\n"), expected);
+}
+
+#[test]
+fn label_and_instruction_share_one_row() {
+    let expected = [
+        "    1  1000  A9 01           2  loop: lda #1",
+        "    2  1002  D0 FC           2      bne loop",
+    ];
+    assert_eq!(listing("loop: lda #1\n    bne loop\n"), expected);
+}
+
+#[test]
+fn long_output_wraps_after_four_bytes() {
+    let expected = [
+        "    1  1000  01 02 03 04            .byte 1, 2, 3, 4, 5, 6, 7, 8, 9",
+        "       1004  05 06 07 08",
+        "       1008  09",
+    ];
+    assert_eq!(listing("    .byte 1, 2, 3, 4, 5, 6, 7, 8, 9\n"), expected);
+}
+
+#[test]
+fn origin_padding_is_left_out() {
+    let expected = [
+        "    1  1000  EA              2      nop",
+        "    2  1010                     .org $1010",
+        "    3  1010  60              6      rts",
+    ];
+    assert_eq!(listing("    nop\n.org $1010\n    rts\n"), expected);
+}
+
+#[test]
+fn lines_without_output_keep_their_text() {
+    let expected = [
+        "    1                           x = 1",
+        "    2                           ; comment",
+        "    3",
+    ];
+    assert_eq!(listing("x = 1\n; comment\n\n"), expected);
+}
+
+#[test]
+fn entries_from_other_files_come_first() {
+    let entries = [
+        ListingEntry { span: Span::new("test.asm", 1, 5), address: 0x080D, bytes: vec![0x60], cycles: Some(6) },
+        ListingEntry { span: Span::new("<command line>", 0, 0), address: 0x0801, bytes: vec![0x0B, 0x08], cycles: None },
+    ];
+    let expected = [
+        "       0801  0B 08",
+        "    1  080D  60              6      rts",
+    ];
+    assert_eq!(format_listing("    rts\n", "test.asm", &entries).lines().collect::<Vec<_>>(), expected);
+}
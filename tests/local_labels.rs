@@ -0,0 +1,46 @@
+// Local label tests for C64 assembly
+
+mod common;
+
+use common::{assemble, assemble_with, symbol};
+use rusm::assembler::Assembler;
+use rusm::parse_source;
+
+#[test]
+fn same_local_name_under_two_globals() {
+    let source = ".org $1000\nfirst:\n.loop:\n    bne .loop\nsecond:\n    nop\n.loop:\n    bne .loop\n";
+    let mut assembler = Assembler::new();
+    let binary = assemble_with(&mut assembler, source);
+    assert_eq!(binary, [0xD0, 0xFE, 0xEA, 0xD0, 0xFE]);
+    assert_eq!(symbol(&assembler, "first.loop"), 0x1000);
+    assert_eq!(symbol(&assembler, "second.loop"), 0x1003);
+}
+
+#[test]
+fn at_prefix_is_a_local_label() {
+    let source = ".org $1000\nstart:\n    nop\n@skip:\n    jmp @skip\n";
+    let mut assembler = Assembler::new();
+    let binary = assemble_with(&mut assembler, source);
+    assert_eq!(binary, [0xEA, 0x4C, 0x01, 0x10]);
+    assert_eq!(symbol(&assembler, "start.skip"), 0x1001);
+}
+
+#[test]
+fn dot_and_at_prefixes_share_the_scope() {
+    assert_eq!(assemble(".org $1000\nstart:\n.loop:\n    jmp @loop\n"), [0x4C, 0x00, 0x10]);
+}
+
+#[test]
+fn local_label_is_reachable_by_its_qualified_name() {
+    let source = ".org $1000\nfirst:\n    rts\n.entry:\n    rts\nsecond:\n    jmp first.entry\n    lda #<first.entry + 1\n";
+    assert_eq!(assemble(source), [0x60, 0x60, 0x4C, 0x01, 0x10, 0xA9, 0x02]);
+}
+
+#[test]
+fn local_label_without_a_global_label_is_an_error() {
+    let error = parse_source(".loop:\n    rts\n").unwrap_err().to_string();
+    assert!(error.contains("Local label .loop without a preceding global label"), "{error}");
+    
+    let error = parse_source("    jmp @skip\n").unwrap_err().to_string();
+    assert!(error.contains("Local label @skip without a preceding global label"), "{error}");
+}
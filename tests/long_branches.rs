@@ -0,0 +1,119 @@
+// Long branch and branch relaxation tests for C64 assembly
+
+mod common;
+
+use common::{assemble, assemble_err, assemble_err_with, assemble_with, symbol};
+use rusm::assembler::Assembler;
+use rusm::isa::Cpu;
+
+/// Long branch pseudo instructions with the opcodes of their branch and of
+/// the inverted branch that skips the JMP
+const LONG_BRANCHES: &[(&str, u8, u8)] = &[
+    ("jcc", 0x90, 0xB0), ("jcs", 0xB0, 0x90), ("jeq", 0xF0, 0xD0), ("jmi", 0x30, 0x10),
+    ("jne", 0xD0, 0xF0), ("jpl", 0x10, 0x30), ("jvc", 0x50, 0x70), ("jvs", 0x70, 0x50),
+];
+
+/// A `.byte` directive emitting `count` zeros
+fn zeros(count: usize) -> String {
+    format!("    .byte {}\n", vec!["0"; count].join(", "))
+}
+
+#[test]
+fn long_branches_in_reach_are_short_branches() {
+    for &(mnemonic, branch, _) in LONG_BRANCHES {
+        let source = format!(".org $1000\n    {mnemonic} target\n    nop\ntarget:\n    rts\n");
+        assert_eq!(assemble(&source), [branch, 0x01, 0xEA, 0x60], "{mnemonic}");
+    }
+}
+
+#[test]
+fn long_branches_out_of_reach_branch_over_a_jmp() {
+    for &(mnemonic, _, inverse) in LONG_BRANCHES {
+        let source = format!(".org $1000\n    {mnemonic} far\n.org $1100\nfar:\n    rts\n");
+        let binary = assemble(&source);
+        assert_eq!(binary[..5], [inverse, 0x03, 0x4C, 0x00, 0x11], "{mnemonic}");
+        assert_eq!(binary.len(), 0x101, "{mnemonic}");
+    }
+}
+
+#[test]
+fn long_branches_reach_backwards() {
+    let source = format!(".org $1000\nnear:\n{}    jne near\n", zeros(126));
+    assert_eq!(assemble(&source)[126..], [0xD0, 0x80]);
+    
+    let source = format!(".org $1000\nfar:\n{}    jne far\n", zeros(127));
+    assert_eq!(assemble(&source)[127..], [0xF0, 0x03, 0x4C, 0x00, 0x10]);
+}
+
+#[test]
+fn long_branch_needs_an_address() {
+    let error = assemble_err("    jeq #1\n");
+    assert!(error.contains("JEQ takes a branch target"), "{error}");
+}
+
+#[test]
+fn branches_out_of_reach_are_only_relaxed_on_request() {
+    let source = ".org $1000\n    bne far\n    beq near\nnear:\n.org $1100\nfar:\n    rts\n";
+    let error = assemble_err(source);
+    assert!(error.contains("too far"), "{error}");
+    
+    let mut assembler = Assembler::new().relax_branches(true);
+    let binary = assemble_with(&mut assembler, source);
+    assert_eq!(binary[..7], [0xF0, 0x03, 0x4C, 0x00, 0x11, 0xF0, 0x00]);
+    assert_eq!(symbol(&assembler, "near"), 0x1007);
+}
+
+#[test]
+fn relaxed_bra_becomes_a_jmp() {
+    let source = ".org $1000\n    bra near\nnear:\n    bra far\n.org $1100\nfar:\n    rts\n";
+    let mut assembler = Assembler::new().cpu(Cpu::Cmos65C02).relax_branches(true);
+    let binary = assemble_with(&mut assembler, source);
+    assert_eq!(binary[..5], [0x80, 0x00, 0x4C, 0x00, 0x11]);
+    
+    let error = assemble_err_with(&mut Assembler::new().cpu(Cpu::Cmos65C02), source);
+    assert!(error.contains("too far"), "{error}");
+}
+
+#[test]
+fn bra_is_not_relaxed_on_the_6510() {
+    let mut assembler = Assembler::new().relax_branches(true);
+    let error = assemble_err_with(&mut assembler, "    bra *\n");
+    assert!(error.contains("BRA is not available on the 6510"), "{error}");
+}
+
+#[test]
+fn relaxed_branch_can_push_an_earlier_branch_out_of_reach() {
+    // `target` is 127 bytes after the first branch until the second branch
+    // grows into a branch over a JMP, which moves it out of reach as well
+    let body = format!("    jne far\n{}target:\n    rts\n.org $2000\nfar:\n    rts\n", zeros(125));
+    let source = format!(".org $1000\n    jeq target\n{body}");
+    let mut assembler = Assembler::new();
+    let binary = assemble_with(&mut assembler, &source);
+    assert_eq!(binary[..10], [0xD0, 0x03, 0x4C, 0x87, 0x10, 0xF0, 0x03, 0x4C, 0x00, 0x20]);
+    assert_eq!(symbol(&assembler, "target"), 0x1087);
+    
+    let source = format!(".org $1000\n    beq target\n{}", body.replace("jne", "bne"));
+    let mut assembler = Assembler::new().relax_branches(true);
+    assert_eq!(assemble_with(&mut assembler, &source)[..10], binary[..10]);
+}
+
+#[test]
+fn branch_stays_long_once_relaxed() {
+    // The loads are absolute until `data` is known to be in zero page, so
+    // `target` is only out of reach in the second pass; the branch keeps its
+    // long form so the layout converges
+    let source = format!(".org $00\n    jeq target\n{}target:\n    rts\ndata:\n    .byte 0\n", "    lda data\n".repeat(50));
+    let mut assembler = Assembler::new();
+    let binary = assemble_with(&mut assembler, &source);
+    assert_eq!(binary[..7], [0xD0, 0x03, 0x4C, 0x69, 0x00, 0xA5, 0x6A]);
+    assert_eq!(symbol(&assembler, "target"), 0x69);
+    assert_eq!(symbol(&assembler, "data"), 0x6A);
+}
+
+#[test]
+fn long_branch_cycles_include_the_jmp() {
+    let mut assembler = Assembler::new();
+    assemble_with(&mut assembler, ".org $1000\n    jeq far\n.org $1100\nfar:\n");
+    let cycles: Vec<_> = assembler.listing().iter().filter_map(|entry| entry.cycles).collect();
+    assert_eq!(cycles, [5]);
+}
@@ -0,0 +1,115 @@
+// Symbol table tests for C64 assembly
+
+mod common;
+
+use common::{assemble_err, assemble_with, symbol};
+use rusm::assembler::Assembler;
+use rusm::ast::Span;
+use rusm::symbols::{Symbol, SymbolFormat, SymbolKind};
+
+#[test]
+fn labels_and_constants_are_listed_in_source_order() {
+    let mut assembler = Assembler::new();
+    assemble_with(&mut assembler, ".org $c000\nscreen = $0400\nstart:\n    sta screen\n");
+    let symbols = assembler.symbols();
+    let names: Vec<_> = symbols.iter().map(|s| (s.name.as_str(), s.kind, s.value)).collect();
+    assert_eq!(names, [("screen", SymbolKind::Constant, 0x0400), ("start", SymbolKind::Label, 0xC000)]);
+}
+
+#[test]
+fn character_constant_keeps_the_encoding_of_its_definition() {
+    let source = ".encoding screen\nletter = 'a'\n    lda #letter\n.encoding ascii\n";
+    let mut assembler = Assembler::new();
+    let binary = assemble_with(&mut assembler, source);
+    assert_eq!(binary, [0xA9, 0x01]);
+    assert_eq!(symbol(&assembler, "letter"), 0x01);
+}
+
+#[test]
+fn constant_using_the_program_counter_keeps_its_definition_address() {
+    let mut assembler = Assembler::new();
+    assemble_with(&mut assembler, ".org $c000\n    nop\nhere = *\n    nop\n");
+    assert_eq!(symbol(&assembler, "here"), 0xC001);
+}
+
+#[test]
+fn unused_constant_with_unknown_reference_is_an_error() {
+    let error = assemble_err("unused = missing + 1\n    rts\n");
+    assert!(error.contains("Unknown label: missing"), "{error}");
+}
+
+#[test]
+fn vice_format_lists_labels_only() {
+    let mut assembler = Assembler::new();
+    assemble_with(&mut assembler, ".org $c000\nscreen = $0400\nstart:\n    nop\nloop:\n    jmp loop\n");
+    let expected = "al C:c000 .start\nal C:c001 .loop\n";
+    assert_eq!(SymbolFormat::Vice.format(&assembler.symbols()), expected);
+}
+
+#[test]
+fn json_format_lists_every_symbol() {
+    let mut assembler = Assembler::new();
+    assemble_with(&mut assembler, ".org $c000\nscreen = $0400\nstart:\n    rts\n");
+    let expected = concat!(
+        "{\n",
+        "  \"symbols\": [\n",
+        "    {\"name\": \"screen\", \"kind\": \"constant\", \"value\": 1024, \"file\": \"<source>\", \"line\": 2, \"column\": 1},\n",
+        "    {\"name\": \"start\", \"kind\": \"label\", \"value\": 49152, \"file\": \"<source>\", \"line\": 3, \"column\": 1}\n",
+        "  ]\n",
+        "}\n",
+    );
+    assert_eq!(SymbolFormat::Json.format(&assembler.symbols()), expected);
+}
+
+#[test]
+fn json_format_escapes_strings() {
+    let symbol = Symbol {
+        name: "start".to_string(),
+        kind: SymbolKind::Label,
+        value: 0x0801,
+        span: Span::new("dir\\\"new\"\tfile\u{1}.asm", 1, 1),
+    };
+    let json = SymbolFormat::Json.format(&[symbol]);
+    assert!(json.contains(r#""file": "dir\\\"new\"\tfile\u0001.asm""#), "{json}");
+}
+
+#[test]
+fn json_format_without_symbols_is_an_empty_list() {
+    assert_eq!(SymbolFormat::Json.format(&[]), "{\n  \"symbols\": []\n}\n");
+    assert_eq!(SymbolFormat::Vice.format(&[]), "");
+}
+
+#[test]
+fn duplicate_label_names_both_definitions() {
+    let error = assemble_err("start:\n    nop\nstart:\n    rts\n");
+    assert!(error.contains("label 'start' already defined at <source>:1:1"), "{error}");
+    assert!(error.contains("<source>:3:1"), "{error}");
+}
+
+#[test]
+fn duplicate_constant_names_both_definitions() {
+    let error = assemble_err("screen = $0400\ncolor = 1\nscreen = $0800\n");
+    assert!(error.contains("constant 'screen' already defined at <source>:1:1"), "{error}");
+    assert!(error.contains("<source>:3:1"), "{error}");
+}
+
+#[test]
+fn label_and_constant_share_one_namespace() {
+    let error = assemble_err("start:\n    rts\nstart = $c000\n");
+    assert!(error.contains("constant 'start' already defined as a label at <source>:1:1"), "{error}");
+    assert!(error.contains("<source>:3:1"), "{error}");
+    
+    let error = assemble_err("start = $c000\nstart:\n    rts\n");
+    assert!(error.contains("label 'start' already defined as a constant at <source>:1:1"), "{error}");
+    assert!(error.contains("<source>:2:1"), "{error}");
+}
+
+#[test]
+fn local_labels_in_different_scopes_are_not_duplicates() {
+    let mut assembler = Assembler::new();
+    assemble_with(&mut assembler, "first:\n.loop:\n    rts\nsecond:\n.loop:\n    rts\n");
+    assert_ne!(symbol(&assembler, "first.loop"), symbol(&assembler, "second.loop"));
+    
+    let error = assemble_err("first:\n.loop:\n    nop\n.loop:\n    rts\n");
+    assert!(error.contains("label 'first.loop' already defined at <source>:2:1"), "{error}");
+}
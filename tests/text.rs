@@ -0,0 +1,104 @@
+// String and character literal tests for C64 assembly
+
+mod common;
+
+use common::{assemble, assemble_err};
+use rusm::Encoding;
+use rusm::parse_source;
+
+#[test]
+fn lone_brace_character_literal_is_a_plain_character() {
+    assert_eq!(assemble("    lda #'{'\n"), [0xA9, 0x7B]);
+    assert_eq!(assemble("    lda #'}'\n"), [0xA9, 0x7D]);
+}
+
+#[test]
+fn lone_brace_in_string_is_a_plain_character() {
+    assert_eq!(assemble(".text \"{\"\n"), [0x7B]);
+    assert_eq!(assemble(".text \"a{b\"\n"), [0x61, 0x7B, 0x62]);
+    assert_eq!(assemble(".text \"{}\"\n"), [0x7B, 0x7D]);
+}
+
+#[test]
+fn escaped_brace_is_a_plain_character() {
+    assert_eq!(assemble(".text \"\\{\"\n"), [0x7B]);
+    assert_eq!(assemble(".text \"\\{clr}\"\n"), b"{clr}");
+    assert_eq!(assemble("    lda #'\\{'\n"), [0xA9, 0x7B]);
+}
+
+#[test]
+fn backslash_escapes_stand_for_the_escaped_character() {
+    assert_eq!(assemble(".text \"\\\"\\\\\\n\"\n"), [0x22, 0x5C, 0x0A]);
+    assert_eq!(assemble("    lda #'\\''\n"), [0xA9, 0x27]);
+}
+
+#[test]
+fn brace_escapes_are_control_codes() {
+    assert_eq!(assemble(".encoding petscii\n.text \"{clr}a{$0d}\"\n"), [0x93, 0x41, 0x0D]);
+    assert_eq!(assemble(".petscii \"{rvs on}{13}\"\n"), [0x12, 0x0D]);
+}
+
+#[test]
+fn unknown_control_code_is_an_error() {
+    let error = parse_source(".text \"{nope}\"\n").unwrap_err().to_string();
+    assert!(error.contains("Unknown control code: {nope}"), "{error}");
+}
+
+#[test]
+fn control_code_is_not_a_character_literal() {
+    let error = parse_source("    lda #'{clr}'\n").unwrap_err().to_string();
+    assert!(error.contains("Invalid character literal: '{clr}'"), "{error}");
+}
+
+#[test]
+fn screen_codes_start_with_the_at_sign() {
+    assert_eq!(Encoding::Screen.encode_char('@'), Some(0x00));
+    assert_eq!(Encoding::Screen.encode_char('A'), Some(0x01));
+    assert_eq!(Encoding::Screen.encode_char('a'), Some(0x01));
+    assert_eq!(Encoding::Screen.encode_char(' '), Some(0x20));
+    assert_eq!(Encoding::Screen.encode_char('['), Some(0x1B));
+    assert_eq!(Encoding::ScreenLower.encode_char('a'), Some(0x01));
+    assert_eq!(Encoding::ScreenLower.encode_char('A'), Some(0x41));
+}
+
+#[test]
+fn petscii_sets_place_capitals_differently() {
+    assert_eq!(Encoding::Petscii.encode_char('A'), Some(0x41));
+    assert_eq!(Encoding::Petscii.encode_char('a'), Some(0x41));
+    assert_eq!(Encoding::PetsciiLower.encode_char('a'), Some(0x41));
+    assert_eq!(Encoding::PetsciiLower.encode_char('A'), Some(0xC1));
+    assert_eq!(Encoding::Petscii.encode_char('£'), Some(0x5C));
+    assert_eq!(Encoding::Petscii.encode_char('\n'), Some(0x0D));
+}
+
+#[test]
+fn characters_without_a_mapping_are_none() {
+    for encoding in [Encoding::Petscii, Encoding::PetsciiLower, Encoding::Screen, Encoding::ScreenLower] {
+        assert_eq!(encoding.encode_char('_'), None, "{encoding}");
+    }
+    assert_eq!(Encoding::Ascii.encode_char('£'), None);
+}
+
+#[test]
+fn encoding_directives_select_the_table() {
+    assert_eq!(assemble(".petscii \"Ab\"\n"), [0x41, 0x42]);
+    assert_eq!(assemble(".scr \"@Ab\"\n"), [0x00, 0x01, 0x02]);
+    assert_eq!(assemble(".encoding petscii_lower\n.text \"Ab\"\n"), [0xC1, 0x42]);
+    assert_eq!(assemble(".encoding screen_lower\n.text \"Ab\"\n    lda #'A'\n"), [0x41, 0x02, 0xA9, 0x41]);
+    assert_eq!(assemble(".encoding scr\n.text \"a\"\n.encoding ascii\n.text \"a\"\n"), [0x01, 0x61]);
+}
+
+#[test]
+fn unmapped_character_is_an_error() {
+    let error = assemble_err(".scr \"a_b\"\n");
+    assert!(error.contains("Character '_' has no mapping in the screen encoding"), "{error}");
+    
+    let error = assemble_err(".encoding petscii\n    lda #'_'\n");
+    assert!(error.contains("Character '_' has no mapping in the petscii encoding"), "{error}");
+}
+
+#[test]
+fn unknown_encoding_is_an_error() {
+    let error = assemble_err(".encoding ebcdic\n");
+    assert!(error.contains("Unknown encoding: ebcdic"), "{error}");
+}
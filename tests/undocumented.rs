@@ -0,0 +1,94 @@
+// Undocumented NMOS opcode tests for C64 assembly
+
+mod common;
+
+use common::assemble_with;
+use rusm::assembler::{Assembler, WarningKind};
+use rusm::ast::Opcode;
+
+/// Source line, emitted bytes, cycles and whether the opcode is unstable
+const ENCODINGS: &[(&str, &[u8], u8, bool)] = &[
+    ("slo $12", &[0x07, 0x12], 5, false),
+    ("aso $1234", &[0x0F, 0x34, 0x12], 6, false),
+    ("rla $12,x", &[0x37, 0x12], 6, false),
+    ("sre ($12),y", &[0x53, 0x12], 8, false),
+    ("lse $1234,y", &[0x5B, 0x34, 0x12], 7, false),
+    ("rra ($12,x)", &[0x63, 0x12], 8, false),
+    ("dcp $1234,x", &[0xDF, 0x34, 0x12], 7, false),
+    ("dcm $12", &[0xC7, 0x12], 5, false),
+    ("isc $12", &[0xE7, 0x12], 5, false),
+    ("isb $1234", &[0xEF, 0x34, 0x12], 6, false),
+    ("ins ($12),y", &[0xF3, 0x12], 8, false),
+    ("sax $12,y", &[0x97, 0x12], 4, false),
+    ("aax $1234", &[0x8F, 0x34, 0x12], 4, false),
+    ("lax $12", &[0xA7, 0x12], 3, false),
+    ("lax $1234,y", &[0xBF, 0x34, 0x12], 4, false),
+    ("lax ($12),y", &[0xB3, 0x12], 5, false),
+    ("lax #$12", &[0xAB, 0x12], 2, true),
+    ("anc #$12", &[0x0B, 0x12], 2, false),
+    ("alr #$12", &[0x4B, 0x12], 2, false),
+    ("asr #$12", &[0x4B, 0x12], 2, false),
+    ("arr #$12", &[0x6B, 0x12], 2, false),
+    ("sbx #$12", &[0xCB, 0x12], 2, false),
+    ("axs #$12", &[0xCB, 0x12], 2, false),
+    ("usbc #$12", &[0xEB, 0x12], 2, false),
+    ("las $1234,y", &[0xBB, 0x34, 0x12], 4, false),
+    ("lar $1234,y", &[0xBB, 0x34, 0x12], 4, false),
+    ("ane #$12", &[0x8B, 0x12], 2, true),
+    ("xaa #$12", &[0x8B, 0x12], 2, true),
+    ("lxa #$12", &[0xAB, 0x12], 2, true),
+    ("atx #$12", &[0xAB, 0x12], 2, true),
+    ("sha $1234,y", &[0x9F, 0x34, 0x12], 5, true),
+    ("sha ($12),y", &[0x93, 0x12], 6, true),
+    ("ahx $1234,y", &[0x9F, 0x34, 0x12], 5, true),
+    ("axa ($12),y", &[0x93, 0x12], 6, true),
+    ("shx $1234,y", &[0x9E, 0x34, 0x12], 5, true),
+    ("sxa $1234,y", &[0x9E, 0x34, 0x12], 5, true),
+    ("shy $1234,x", &[0x9C, 0x34, 0x12], 5, true),
+    ("sya $1234,x", &[0x9C, 0x34, 0x12], 5, true),
+    ("tas $1234,y", &[0x9B, 0x34, 0x12], 5, true),
+    ("shs $1234,y", &[0x9B, 0x34, 0x12], 5, true),
+    ("nop #$12", &[0x80, 0x12], 2, false),
+    ("dop #$12", &[0x80, 0x12], 2, false),
+    ("skb $12", &[0x04, 0x12], 3, false),
+    ("dop $12,x", &[0x14, 0x12], 4, false),
+    ("nop $1234", &[0x0C, 0x34, 0x12], 4, false),
+    ("top $1234", &[0x0C, 0x34, 0x12], 4, false),
+    ("top $12", &[0x0C, 0x12, 0x00], 4, false),
+    ("skw $12,x", &[0x1C, 0x12, 0x00], 4, false),
+    ("jam", &[0x02], 0, false),
+    ("kil", &[0x02], 0, false),
+    ("hlt", &[0x02], 0, false),
+    ("hcf", &[0x02], 0, false),
+];
+
+#[test]
+fn undocumented_opcodes_encode_with_their_cycles() {
+    for &(line, bytes, cycles, unstable) in ENCODINGS {
+        let mut assembler = Assembler::new();
+        let binary = assemble_with(&mut assembler, &format!("    {line}\n"));
+        assert_eq!(binary, bytes, "{line}");
+        assert_eq!(assembler.listing()[0].cycles, Some(cycles), "{line}");
+        assert_eq!(!assembler.warnings().is_empty(), unstable, "{line}");
+    }
+}
+
+#[test]
+fn unstable_opcodes_warn_once_per_use() {
+    let mut assembler = Assembler::new();
+    assemble_with(&mut assembler, "    lax #0\n    nop\n    xaa #0\n");
+    let warnings: Vec<_> = assembler.warnings().iter().map(|w| (w.span.line, &w.kind)).collect();
+    assert_eq!(warnings, [
+        (1, &WarningKind::UnstableOpcode(Opcode::LAX)),
+        (3, &WarningKind::UnstableOpcode(Opcode::ANE)),
+    ]);
+}
+
+#[test]
+fn allow_unstable_suppresses_the_warnings() {
+    for &(line, bytes, _, _) in ENCODINGS {
+        let mut assembler = Assembler::new().allow_unstable(true);
+        assert_eq!(assemble_with(&mut assembler, &format!("    {line}\n")), bytes, "{line}");
+        assert!(assembler.warnings().is_empty(), "{line}");
+    }
+}
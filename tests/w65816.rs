@@ -0,0 +1,144 @@
+// 65816 instruction set tests for C64 assembly
+
+mod common;
+
+use common::{assemble_err, assemble_err_with, assemble_with, symbol};
+use rusm::assembler::Assembler;
+use rusm::ast::Ast;
+use rusm::isa::Cpu;
+use rusm::{parse_source, OutputFormat};
+
+/// Assemble `source` for the 65816
+fn assemble(source: &str) -> Vec<u8> {
+    assemble_with(&mut Assembler::new().cpu(Cpu::W65816), source)
+}
+
+/// Assemble `source` for the 65816 and return the error message
+fn assemble_err_65816(source: &str) -> String {
+    assemble_err_with(&mut Assembler::new().cpu(Cpu::W65816), source)
+}
+
+#[test]
+fn long_and_stack_relative_addressing_modes() {
+    let source = "    lda $123456\n    sta $123456,x\n    lda.l $1234\n    lda [$12]\n    lda [$12],y\n    \
+                  lda $03,s\n    lda ($03,s),y\n    jml [$1234]\n    jsl $123456\n";
+    assert_eq!(assemble(source), [
+        0xAF, 0x56, 0x34, 0x12,
+        0x9F, 0x56, 0x34, 0x12,
+        0xAF, 0x34, 0x12, 0x00,
+        0xA7, 0x12,
+        0xB7, 0x12,
+        0xA3, 0x03,
+        0xB3, 0x03,
+        0xDC, 0x34, 0x12,
+        0x22, 0x56, 0x34, 0x12,
+    ]);
+}
+
+#[test]
+fn implied_and_immediate_65816_instructions() {
+    let source = "    xba\n    xce\n    rtl\n    phb\n    tcd\n    rep #$30\n    sep #$20\n    cop #1\n    wdm #2\n    pea $1234\n";
+    assert_eq!(assemble(source), [
+        0xEB, 0xFB, 0x6B, 0x8B, 0x5B, 0xC2, 0x30, 0xE2, 0x20, 0x02, 0x01, 0x42, 0x02, 0xF4, 0x34, 0x12,
+    ]);
+}
+
+#[test]
+fn long_relative_branches_count_from_the_next_instruction() {
+    let source = ".org $1000\n-\n    brl -\n    per +\n    nop\n+\n";
+    assert_eq!(assemble(source), [0x82, 0xFD, 0xFF, 0x62, 0x01, 0x00, 0xEA]);
+}
+
+#[test]
+fn block_moves_encode_destination_before_source() {
+    assert_eq!(assemble("    mvn $01, $02\n    mvp 3, 4\n"), [0x54, 0x02, 0x01, 0x44, 0x04, 0x03]);
+    let error = assemble_err_65816("    mvn $100, 2\n");
+    assert!(error.contains("Source bank"), "{error}");
+}
+
+#[test]
+fn bank_byte_operator_selects_bits_16_to_23() {
+    assert_eq!(assemble("    lda #^$123456\n    ldx #>$123456\n"), [0xA9, 0x12, 0xA2, 0x34]);
+}
+
+#[test]
+fn register_width_directives_size_immediates() {
+    let source = ".a16\n    lda #$1234\n    ldx #$12\n.i16\n    ldy #$1234\n    cpx #$12\n.a8\n.i8\n    and #$12\n    cpy #$12\n";
+    assert_eq!(assemble(source), [
+        0xA9, 0x34, 0x12, 0xA2, 0x12, 0xA0, 0x34, 0x12, 0xE0, 0x12, 0x00, 0x29, 0x12, 0xC0, 0x12,
+    ]);
+}
+
+#[test]
+fn wide_immediates_take_a_cycle_more() {
+    let mut assembler = Assembler::new().cpu(Cpu::W65816);
+    assemble_with(&mut assembler, "    lda #0\n.a16\n    lda #0\n");
+    let cycles: Vec<_> = assembler.listing().iter().filter_map(|entry| entry.cycles).collect();
+    assert_eq!(cycles, [2, 3]);
+}
+
+#[test]
+fn rep_and_sep_only_change_widths_in_smart_mode() {
+    assert_eq!(assemble("    rep #$20\n    lda #$12\n"), [0xC2, 0x20, 0xA9, 0x12]);
+    let error = assemble_err_65816("    rep #$20\n    lda #$1234\n");
+    assert!(error.contains("Immediate value"), "{error}");
+    
+    let source = ".smart\n    rep #$20\n    lda #$1234\n    ldx #$12\n    sep #$20\n    rep #$10\n    lda #$12\n    ldx #$1234\n";
+    assert_eq!(assemble(source), [
+        0xC2, 0x20, 0xA9, 0x34, 0x12, 0xA2, 0x12, 0xE2, 0x20, 0xC2, 0x10, 0xA9, 0x12, 0xA2, 0x34, 0x12,
+    ]);
+}
+
+#[test]
+fn smart_mode_can_be_switched_off_and_set_by_the_builder() {
+    let source = ".smart\n    rep #$30\n.smart off\n    sep #$30\n    lda #$1234\n";
+    assert_eq!(assemble(source), [0xC2, 0x30, 0xE2, 0x30, 0xA9, 0x34, 0x12]);
+    
+    let mut assembler = Assembler::new().cpu(Cpu::W65816).smart(true);
+    assert_eq!(assemble_with(&mut assembler, "    rep #$10\n    ldy #$1234\n"), [0xC2, 0x10, 0xA0, 0x34, 0x12]);
+}
+
+#[test]
+fn immediate_sizes_after_rep_move_later_labels() {
+    let source = ".org $1000\n.smart\n    rep #$20\n    lda #0\n    sep #$20\n    lda #0\nend:\n";
+    let mut assembler = Assembler::new().cpu(Cpu::W65816);
+    assemble_with(&mut assembler, source);
+    assert_eq!(symbol(&assembler, "end"), 0x1009);
+}
+
+#[test]
+fn register_widths_are_reset_for_every_assembly() {
+    let mut assembler = Assembler::new().cpu(Cpu::W65816);
+    assemble_with(&mut assembler, ".a16\n.i16\n");
+    assert_eq!(assemble_with(&mut assembler, "    lda #1\n"), [0xA9, 0x01]);
+}
+
+#[test]
+fn code_can_be_placed_above_bank_zero() {
+    let source = ".cpu 65816\n.org $012000\nstart:\n    jsr sub\n    jmp start\n    lda data\nsub:\n    rts\ndata:\n    .byte 1\n";
+    let mut assembler = Assembler::new();
+    let binary = assemble_with(&mut assembler, source);
+    assert_eq!(assembler.origin(), 0x012000);
+    assert_eq!(binary, [
+        0x20, 0x0A, 0x20,
+        0x4C, 0x00, 0x20,
+        0xAF, 0x0B, 0x20, 0x01,
+        0x60,
+        0x01,
+    ]);
+}
+
+#[test]
+fn code_above_bank_zero_has_no_prg_load_address() {
+    let ast: Ast = parse_source(".cpu 65816\n.org $012000\n    rts\n").unwrap();
+    let mut assembler = Assembler::new();
+    assert_eq!(assembler.assemble_with_format(&ast, OutputFormat::Raw).unwrap(), [0x60]);
+    let error = assembler.assemble_with_format(&ast, OutputFormat::Prg).unwrap_err().to_string();
+    assert!(error.contains("does not fit the load address of a prg file"), "{error}");
+}
+
+#[test]
+fn origin_above_bank_zero_needs_the_65816() {
+    let error = assemble_err(".org $012000\n    rts\n");
+    assert!(error.contains("Origin out of range: 73728"), "{error}");
+}